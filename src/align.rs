@@ -0,0 +1,198 @@
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::io;
+
+use crate::tables;
+use crate::text::{self, TextEntry};
+use crate::util;
+
+//two decodings of the same table, like the japanese original and an official translation, can be
+//lined up side by side. their text is matched by its key from text.rs, so items are matched by id
+//and book lines by book, page and line. either side can be a table or its json, so a table in
+//another encoding can be decoded with its own --encoding first. text that is only on one side and
+//text in books or pages whose page or line counts differ is flagged, as the rest of those might be
+//matched up wrong
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    Json,
+    Csv,
+    Tsv,
+}
+
+#[derive(Serialize)]
+struct AlignedEntry {
+    key: String,
+    source: Option<String>,
+    target: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    flags: Vec<String>,
+}
+
+pub fn align(source_path: String, target_path: String, format: Format) -> io::Result<()> {
+    let source_table = tables::table_name(&text::file_name(&source_path)?);
+    let target_table = tables::table_name(&text::file_name(&target_path)?);
+    if source_table != target_table {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} and {} are different tables, {} and {}",
+                source_path, target_path, source_table, target_table
+            ),
+        ));
+    }
+
+    let aligned = align_entries(
+        &source_table,
+        &text::read_entries(&source_path)?,
+        &text::read_entries(&target_path)?,
+    );
+    let flagged = aligned
+        .iter()
+        .filter(|entry| !entry.flags.is_empty())
+        .count();
+
+    let (extension, data) = match format {
+        Format::Json => (
+            "bilingual.json",
+            serde_json::to_string_pretty(&aligned)
+                .map_err(io::Error::other)?
+                .into_bytes(),
+        ),
+        Format::Csv => ("bilingual.csv", to_sheet(&aligned, b',')?),
+        Format::Tsv => ("bilingual.tsv", to_sheet(&aligned, b'\t')?),
+    };
+    util::write_output(&source_path, extension, &data)?;
+
+    println!("Aligned {} entries, {} flagged", aligned.len(), flagged);
+    Ok(())
+}
+
+//pair up the entries of both sides by key, in the order of the source with the entries that are only
+//in the target after them
+fn align_entries(table: &str, source: &[TextEntry], target: &[TextEntry]) -> Vec<AlignedEntry> {
+    let source_shape = Shape::of(table, source);
+    let target_shape = Shape::of(table, target);
+
+    let mut target_texts: HashMap<&str, &str> = target
+        .iter()
+        .map(|entry| (entry.key.as_str(), entry.text.as_str()))
+        .collect();
+    let mut aligned: Vec<AlignedEntry> = source
+        .iter()
+        .map(|entry| AlignedEntry {
+            key: entry.key.clone(),
+            source: Some(entry.text.clone()),
+            target: target_texts.remove(entry.key.as_str()).map(String::from),
+            flags: Vec::new(),
+        })
+        .collect();
+    aligned.extend(
+        target
+            .iter()
+            .filter(|entry| target_texts.contains_key(entry.key.as_str()))
+            .map(|entry| AlignedEntry {
+                key: entry.key.clone(),
+                source: None,
+                target: Some(entry.text.clone()),
+                flags: Vec::new(),
+            }),
+    );
+
+    for entry in &mut aligned {
+        if entry.source.is_none() {
+            entry.flags.push("missing from the source".to_string());
+        }
+        if entry.target.is_none() {
+            entry.flags.push("missing from the target".to_string());
+        }
+        if let [book, rest @ ..] = key_ids(table, &entry.key).as_slice() {
+            let pages = (source_shape.pages(book), target_shape.pages(book));
+            if pages.0 != pages.1 {
+                entry.flags.push(format!(
+                    "book {} has {} pages in the source and {} in the target",
+                    book, pages.0, pages.1
+                ));
+            }
+            if let [page, _] = rest {
+                let lines = (
+                    source_shape.lines(book, page),
+                    target_shape.lines(book, page),
+                );
+                if lines.0 != lines.1 {
+                    entry.flags.push(format!(
+                        "page {} of book {} has {} lines in the source and {} in the target",
+                        page, book, lines.0, lines.1
+                    ));
+                }
+            }
+        }
+    }
+
+    aligned
+}
+
+//the ids in the key of a book title or line, e.g. ["2", "1", "0"] for "t_book03/2/1/0". items don't
+//have pages or lines to compare, so they have none
+fn key_ids<'a>(table: &str, key: &'a str) -> Vec<&'a str> {
+    if !table.starts_with("t_book") {
+        return Vec::new();
+    }
+    key[table.len() + 1..]
+        .split('/')
+        .filter(|id| *id != "title")
+        .collect()
+}
+
+//the pages of every book and the lines of every page on one side
+struct Shape<'a> {
+    pages: HashMap<&'a str, BTreeSet<&'a str>>,
+    lines: HashMap<(&'a str, &'a str), usize>,
+}
+
+impl<'a> Shape<'a> {
+    fn of(table: &str, entries: &'a [TextEntry]) -> Self {
+        let mut shape = Shape {
+            pages: HashMap::new(),
+            lines: HashMap::new(),
+        };
+        for entry in entries {
+            if let [book, page, _] = key_ids(table, &entry.key).as_slice() {
+                shape.pages.entry(book).or_default().insert(page);
+                *shape.lines.entry((book, page)).or_default() += 1;
+            }
+        }
+        shape
+    }
+
+    fn pages(&self, book: &str) -> usize {
+        self.pages.get(book).map_or(0, BTreeSet::len)
+    }
+
+    fn lines(&self, book: &str, page: &str) -> usize {
+        self.lines.get(&(book, page)).copied().unwrap_or(0)
+    }
+}
+
+fn to_sheet(aligned: &[AlignedEntry], delimiter: u8) -> io::Result<Vec<u8>> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(Vec::new());
+    writer
+        .write_record(["key", "source", "target", "flags"])
+        .map_err(io::Error::other)?;
+    for entry in aligned {
+        writer
+            .write_record([
+                entry.key.as_str(),
+                entry.source.as_deref().unwrap_or(""),
+                entry.target.as_deref().unwrap_or(""),
+                &entry.flags.join("; "),
+            ])
+            .map_err(io::Error::other)?;
+    }
+
+    writer
+        .into_inner()
+        .map_err(|e| io::Error::other(e.to_string()))
+}
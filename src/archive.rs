@@ -0,0 +1,391 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::compression;
+use crate::tables;
+use crate::util;
+
+//the sky games keep their files in pairs of archives. ED6_DTxx.dir is the index: an 8 byte magic, a
+//u64 entry count and then a 36 byte entry per file. ED6_DTxx.dat holds the data: an 8 byte magic,
+//the same u64 count, a u32 offset for every file plus one for the end of the last file, and then
+//the files themselves
+pub const DIR_MAGIC: &[u8; 8] = b"LB DIR\x1A\0";
+pub const DAT_MAGIC: &[u8; 8] = b"LB DAT\x1A\0";
+
+pub struct Entry {
+    //the 8.3 file name as stored in the index, e.g. "T_NAME  ._DT"
+    pub raw_name: [u8; 12],
+    pub unknown1: u32,
+    //size of the file as stored in the .dat
+    pub size: u32,
+    pub unknown2: u32,
+    //space reserved for the file in the .dat
+    pub capacity: u32,
+    pub timestamp: u32,
+    pub offset: u32,
+}
+
+impl Entry {
+    //the file name in the form used everywhere else, e.g. "t_name._dt"
+    pub fn name(&self) -> String {
+        let raw = String::from_utf8_lossy(&self.raw_name);
+        let mut parts = raw.splitn(2, '.');
+        let stem = parts.next().unwrap_or("").trim_end();
+        match parts.next() {
+            Some(extension) => format!("{}.{}", stem, extension.trim_end()),
+            None => stem.to_string(),
+        }
+        .to_ascii_lowercase()
+    }
+}
+
+pub fn list_archive(dir_path: String) -> io::Result<()> {
+    let entries = read_dir(&dir_path)?;
+
+    println!(
+        "{:>5}  {:<12}  {:>8}  {:>8}  {:>10}  {:>10}  {:>10}  {:>10}",
+        "index", "name", "size", "capacity", "offset", "timestamp", "unknown1", "unknown2"
+    );
+    for (i, entry) in entries.iter().enumerate() {
+        println!(
+            "{:>5}  {:<12}  {:>8}  {:>8}  {:>#10X}  {:>10}  {:>#10X}  {:>#10X}",
+            i,
+            entry.name(),
+            entry.size,
+            entry.capacity,
+            entry.offset,
+            entry.timestamp,
+            entry.unknown1,
+            entry.unknown2
+        );
+    }
+
+    Ok(())
+}
+
+pub fn convert_archived_table_to_json_file(dir_path: String, name: String) -> io::Result<()> {
+    let entries = read_dir(&dir_path)?;
+    let entry = find_entry(&entries, &name)?;
+    let data = read_table(&dat_path(&dir_path), entry)?;
+
+    let json = tables::table_to_json(&entry.name(), &data)?;
+    util::write_output(&entry.name(), "json", json.as_bytes())
+}
+
+//write a copy of an archive pair with the given tables replaced or added. inputs can be ._dt files or
+//json, which is encoded with the codec matching its name first. replaced tables are compressed if
+//the ones they replace were, added ones only if compress is set. the original archive is never
+//touched, the result is written to output_path and the .dat next to it
+pub fn pack_archive(
+    dir_path: String,
+    output_path: String,
+    inputs: Vec<String>,
+    compress: bool,
+) -> io::Result<()> {
+    let input_dat_path = dat_path(&dir_path);
+    let output_dat_path = dat_path(&output_path);
+    if same_file(&dir_path, &output_path) || same_file(&input_dat_path, &output_dat_path) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "The output archive must not be the input archive",
+        ));
+    }
+
+    let mut entries = read_dir(&dir_path)?;
+    let mut contents = entries
+        .iter()
+        .map(|entry| read_entry(&input_dat_path, entry))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as u32);
+
+    for input in &inputs {
+        let (name, data) = read_input(input)?;
+        let raw_name = raw_name(&name)?;
+
+        //replaced tables keep their place in the archive, new ones go at the end
+        let (index, compressed) = match entries.iter().position(|e| e.name() == name) {
+            Some(index) => (index, compression::is_compressed(&contents[index])),
+            None => {
+                entries.push(Entry {
+                    raw_name,
+                    unknown1: 0,
+                    size: 0,
+                    unknown2: 0,
+                    capacity: 0,
+                    timestamp: 0,
+                    offset: 0,
+                });
+                contents.push(Vec::new());
+                (entries.len() - 1, compress)
+            }
+        };
+        //inputs that were compressed beforehand are stored as they are
+        let data = if compressed && !compression::is_compressed(&data) {
+            compression::compress(&data)
+        } else {
+            data
+        };
+
+        let entry = &mut entries[index];
+        entry.size = data.len() as u32;
+        entry.capacity = data.len() as u32;
+        entry.timestamp = timestamp;
+        contents[index] = data;
+        println!(
+            "Packed {} as {}{}",
+            input,
+            name,
+            if compressed { " (compressed)" } else { "" }
+        );
+    }
+
+    write_archive(&output_path, &output_dat_path, &mut entries, &contents)
+}
+
+//the .dat that goes with a .dir
+pub fn dat_path(dir_path: &str) -> String {
+    Path::new(dir_path)
+        .with_extension("dat")
+        .to_string_lossy()
+        .into_owned()
+}
+
+pub fn find_entry<'a>(entries: &'a [Entry], name: &str) -> io::Result<&'a Entry> {
+    let name = name.to_ascii_lowercase();
+    entries.iter().find(|e| e.name() == name).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} is not in the archive", name),
+        )
+    })
+}
+
+//NOTE: code for reading archives------------------------------------------------------------------
+pub fn read_dir(path: &str) -> io::Result<Vec<Entry>> {
+    let mut file = File::open(path)?;
+    check_magic(&mut file, DIR_MAGIC, path)?;
+    let count = read_u64(&mut file)?;
+
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        let mut raw_name = [0u8; 12];
+        file.read_exact(&mut raw_name)?;
+        entries.push(Entry {
+            raw_name,
+            unknown1: util::read_u32(&mut file)?,
+            size: util::read_u32(&mut file)?,
+            unknown2: util::read_u32(&mut file)?,
+            capacity: util::read_u32(&mut file)?,
+            timestamp: util::read_u32(&mut file)?,
+            offset: util::read_u32(&mut file)?,
+        });
+    }
+
+    Ok(entries)
+}
+
+pub fn read_entry(dat_path: &str, entry: &Entry) -> io::Result<Vec<u8>> {
+    let mut file = File::open(dat_path)?;
+    check_magic(&mut file, DAT_MAGIC, dat_path)?;
+
+    file.seek(SeekFrom::Start(entry.offset as u64))?;
+    let mut data = vec![0u8; entry.size as usize];
+    file.read_exact(&mut data).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Failed to read {} from {}: {}", entry.name(), dat_path, e),
+        )
+    })?;
+
+    Ok(data)
+}
+
+//read a table to pack along with its name in the archive
+fn read_input(path: &str) -> io::Result<(String, Vec<u8>)> {
+    let file_name = Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_ascii_lowercase())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Not a valid file path."))?;
+
+    match file_name.strip_suffix(".json") {
+        Some(stem) => {
+            let name = format!("{}._dt", stem);
+            let data = tables::json_to_table(&name, &fs::read_to_string(path)?)?;
+            Ok((name, data))
+        }
+        None => Ok((file_name, fs::read(path)?)),
+    }
+}
+
+fn same_file(a: &str, b: &str) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+//NOTE: code for writing archives------------------------------------------------------------------
+fn write_archive(
+    dir_path: &str,
+    dat_path: &str,
+    entries: &mut [Entry],
+    contents: &[Vec<u8>],
+) -> io::Result<()> {
+    let count = entries.len() as u64;
+
+    //the files are laid out back to back after the offset table
+    let mut offset = 16 + 4 * (entries.len() + 1);
+    let mut dat = Vec::new();
+    dat.extend_from_slice(DAT_MAGIC);
+    dat.extend(count.to_le_bytes());
+    let mut offsets = Vec::new();
+    for (entry, data) in entries.iter_mut().zip(contents) {
+        entry.offset = offset as u32;
+        offsets.push(offset as u32);
+        offset += data.len();
+    }
+    offsets.push(offset as u32);
+    for offset in offsets {
+        dat.extend(offset.to_le_bytes());
+    }
+    for data in contents {
+        dat.extend_from_slice(data);
+    }
+
+    let mut dir = Vec::new();
+    dir.extend_from_slice(DIR_MAGIC);
+    dir.extend(count.to_le_bytes());
+    for entry in entries.iter() {
+        dir.extend_from_slice(&entry.raw_name);
+        for value in [
+            entry.unknown1,
+            entry.size,
+            entry.unknown2,
+            entry.capacity,
+            entry.timestamp,
+            entry.offset,
+        ] {
+            dir.extend(value.to_le_bytes());
+        }
+    }
+
+    fs::write(dat_path, dat)?;
+    fs::write(dir_path, dir)
+}
+
+//the 8.3 form of a file name used in the index, e.g. "T_NAME  ._DT" for "t_name._dt"
+fn raw_name(name: &str) -> io::Result<[u8; 12]> {
+    let (stem, extension) = name.split_once('.').unwrap_or((name, ""));
+    if stem.is_empty() || stem.len() > 8 || extension.len() > 3 || !name.is_ascii() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a valid 8.3 file name", name),
+        ));
+    }
+
+    let raw = format!("{:<8}.{:<3}", stem, extension).to_ascii_uppercase();
+    let mut bytes = [0u8; 12];
+    bytes.copy_from_slice(raw.as_bytes());
+    Ok(bytes)
+}
+
+//read an entry, decompressing it if it is stored compressed
+pub fn read_table(dat_path: &str, entry: &Entry) -> io::Result<Vec<u8>> {
+    let data = read_entry(dat_path, entry)?;
+    if compression::is_compressed(&data) {
+        return compression::decompress(&data);
+    }
+    Ok(data)
+}
+
+fn check_magic(file: &mut File, magic: &[u8; 8], path: &str) -> io::Result<()> {
+    let mut bytes = [0u8; 8];
+    file.read_exact(&mut bytes)?;
+    if &bytes != magic {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is not a sky archive", path),
+        ));
+    }
+    Ok(())
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, size: usize) -> Entry {
+        Entry {
+            raw_name: raw_name(name).unwrap(),
+            unknown1: 0,
+            size: size as u32,
+            unknown2: 0,
+            capacity: size as u32,
+            timestamp: 0,
+            offset: 0,
+        }
+    }
+
+    #[test]
+    fn raw_names_are_padded_8_3_names() {
+        assert_eq!(&raw_name("t_name._dt").unwrap(), b"T_NAME  ._DT");
+        assert_eq!(entry("t_name._dt", 0).name(), "t_name._dt");
+        assert!(raw_name("t_longname._dt").is_err());
+        assert!(raw_name("t_name._dat").is_err());
+    }
+
+    #[test]
+    fn written_archives_index_every_file() {
+        let folder = std::env::temp_dir().join(format!("gospel_archive_{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let dir_path = folder.join("ED6_DT99.dir").to_string_lossy().into_owned();
+        let dat_path = dat_path(&dir_path);
+
+        let contents = vec![b"abc".to_vec(), Vec::new(), b"defgh".to_vec()];
+        let mut entries = vec![
+            entry("t_name._dt", 3),
+            entry("t_face._dt", 0),
+            entry("t_town._dt", 5),
+        ];
+        write_archive(&dir_path, &dat_path, &mut entries, &contents).unwrap();
+
+        //16 byte header and four offsets before the first file
+        let entries = read_dir(&dir_path).unwrap();
+        let offsets: Vec<u32> = entries.iter().map(|entry| entry.offset).collect();
+        assert_eq!(offsets, [32, 35, 35]);
+        let sizes: Vec<u32> = entries.iter().map(|entry| entry.size).collect();
+        assert_eq!(sizes, [3, 0, 5]);
+        assert_eq!(entries[2].name(), "t_town._dt");
+
+        let dir = fs::read(&dir_path).unwrap();
+        assert_eq!(dir.len(), 16 + 36 * 3);
+        assert_eq!(&dir[8..16], &3u64.to_le_bytes());
+
+        let dat = fs::read(&dat_path).unwrap();
+        assert_eq!(&dat[..8], DAT_MAGIC);
+        assert_eq!(&dat[8..16], &3u64.to_le_bytes());
+        let table: Vec<u32> = dat[16..32]
+            .chunks(4)
+            .map(|offset| u32::from_le_bytes(offset.try_into().unwrap()))
+            .collect();
+        assert_eq!(table, [32, 35, 35, 40]);
+        assert_eq!(dat.len(), 40);
+
+        for (entry, data) in entries.iter().zip(&contents) {
+            assert_eq!(&read_entry(&dat_path, entry).unwrap(), data);
+        }
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
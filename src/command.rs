@@ -3,14 +3,52 @@ use clap::{Parser, Subcommand};
 use std::fmt::Display;
 use std::process;
 
+use crate::align;
+use crate::archive;
+use crate::compression;
+use crate::config;
+use crate::diff;
+use crate::encoding::{self, Encoding};
+use crate::game::{self, Game};
+use crate::glyphs;
+use crate::inspect;
+use crate::normalize;
+use crate::po;
+use crate::raw;
+use crate::schema;
+use crate::sheet;
+use crate::tables::t_bgmtbl;
 use crate::tables::t_book;
+use crate::tables::t_cook;
+use crate::tables::t_face;
 use crate::tables::t_item2;
+use crate::tables::t_name;
+use crate::tables::t_quest;
+use crate::tables::t_sltget;
+use crate::tables::t_status;
+use crate::tables::t_town;
+use crate::xliff;
 
 #[derive(Parser)]
 #[command(author, version, about)]
 pub struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+    /// Game the tables belong to. detected from ED6_DTxx archive names in the input path if not given
+    #[arg(long, global = true, value_enum)]
+    game: Option<Game>,
+    /// Text encoding of the tables' strings. overrides the encoding in gospel.json, defaults to
+    /// cp932
+    #[arg(long, global = true, value_enum)]
+    encoding: Option<Encoding>,
+    /// Json file mapping letters to the code points a patched font draws them at. overrides the
+    /// glyph map in gospel.json
+    #[arg(long, global = true)]
+    glyphs: Option<String>,
+    /// Replace curly quotes, dashes, ellipses and non-breaking spaces before encoding and report
+    /// the replacements. can also be turned on in gospel.json
+    #[arg(long, global = true)]
+    normalize: bool,
 }
 
 #[derive(Subcommand)]
@@ -19,6 +57,9 @@ pub enum Commands {
     TBookToJson {
         /// Input file path for the t_bookXX._dt file
         input_path: String,
+        /// Path to a t_face._dt file used to add portrait file names next to image ids
+        #[arg(long)]
+        faces: Option<String>,
     },
     /// Decode t_items2._dt to json
     TItem2ToJson {
@@ -33,15 +74,275 @@ pub enum Commands {
         /// Input file path for the json representation of a t_items2._dt file
         input_path: String,
     },
+    /// Decode t_town._dt to json
+    TTownToJson {
+        /// Input file path for the t_town._dt file
+        input_path: String,
+    },
+    /// Encode t_town.json to _dt
+    JsonToTTown {
+        /// Input file path for the json representation of a t_town._dt file
+        input_path: String,
+    },
+    /// Decode t_name._dt to json
+    TNameToJson {
+        /// Input file path for the t_name._dt file
+        input_path: String,
+    },
+    /// Encode t_name.json to _dt
+    JsonToTName {
+        /// Input file path for the json representation of a t_name._dt file
+        input_path: String,
+    },
+    /// Decode t_status._dt to json
+    TStatusToJson {
+        /// Input file path for the t_status._dt file
+        input_path: String,
+    },
+    /// Encode t_status.json to _dt
+    JsonToTStatus {
+        /// Input file path for the json representation of a t_status._dt file
+        input_path: String,
+    },
+    /// Export t_status._dt to csv with one row per character level
+    TStatusToCsv {
+        /// Input file path for the t_status._dt file
+        input_path: String,
+    },
+    /// Encode t_status.csv to _dt
+    CsvToTStatus {
+        /// Input file path for the csv representation of a t_status._dt file
+        input_path: String,
+    },
+    /// Decode t_sltget._dt to json
+    TSltgetToJson {
+        /// Input file path for the t_sltget._dt file
+        input_path: String,
+    },
+    /// Encode t_sltget.json to _dt
+    JsonToTSltget {
+        /// Input file path for the json representation of a t_sltget._dt file
+        input_path: String,
+    },
+    /// Decode t_cook._dt to json
+    TCookToJson {
+        /// Input file path for the t_cook._dt file
+        input_path: String,
+        /// Path to a t_item2._dt file used to add item names next to item ids
+        #[arg(long)]
+        items: Option<String>,
+    },
+    /// Encode t_cook.json to _dt
+    JsonToTCook {
+        /// Input file path for the json representation of a t_cook._dt file
+        input_path: String,
+    },
+    /// Decode t_quest._dt to json
+    TQuestToJson {
+        /// Input file path for the t_quest._dt file
+        input_path: String,
+    },
+    /// Encode t_quest.json to _dt
+    JsonToTQuest {
+        /// Input file path for the json representation of a t_quest._dt file
+        input_path: String,
+    },
+    /// Decode t_face._dt to json
+    TFaceToJson {
+        /// Input file path for the t_face._dt file
+        input_path: String,
+    },
+    /// Encode t_face.json to _dt
+    JsonToTFace {
+        /// Input file path for the json representation of a t_face._dt file
+        input_path: String,
+    },
+    /// Decode t_bgmtbl._dt to json
+    TBgmtblToJson {
+        /// Input file path for the t_bgmtbl._dt file
+        input_path: String,
+    },
+    /// Encode t_bgmtbl.json to _dt
+    JsonToTBgmtbl {
+        /// Input file path for the json representation of a t_bgmtbl._dt file
+        input_path: String,
+    },
+    /// Decode any fixed layout _dt to json using a schema describing its records
+    DtToJson {
+        /// Input file path for the _dt file
+        input_path: String,
+        /// Path to the json schema describing the table's records
+        #[arg(long)]
+        schema: String,
+    },
+    /// Encode json to _dt using a schema describing its records
+    JsonToDt {
+        /// Input file path for the json representation of the _dt file
+        input_path: String,
+        /// Path to the json schema describing the table's records
+        #[arg(long)]
+        schema: String,
+    },
+    /// Dump any pointer indexed _dt to json as raw record bytes and the strings found in them
+    RawToJson {
+        /// Input file path for the _dt file
+        input_path: String,
+    },
+    /// Encode a raw json dump back to _dt, applying any edited strings
+    JsonToRaw {
+        /// Input file path for the raw json representation of the _dt file
+        input_path: String,
+    },
+    /// Print the pointer list, records, text and control codes of a _dt file
+    Inspect {
+        /// Input file path for the _dt file
+        input_path: String,
+    },
+    /// List the files in an ED6_DTxx.dir/.dat archive pair
+    ArchiveList {
+        /// Input file path for the .dir file, the .dat is expected next to it
+        dir_path: String,
+    },
+    /// Decode a table straight out of an ED6_DTxx.dir/.dat archive pair to json
+    ArchiveToJson {
+        /// Input file path for the .dir file, the .dat is expected next to it
+        dir_path: String,
+        /// Name of the table in the archive, e.g. t_book03._dt
+        name: String,
+    },
+    /// Write a copy of an ED6_DTxx.dir/.dat archive pair with tables replaced or added
+    ArchivePack {
+        /// Input file path for the .dir file, the .dat is expected next to it
+        dir_path: String,
+        /// Output file path for the new .dir file, the .dat is written next to it
+        #[arg(long)]
+        output: String,
+        /// ._dt or json files to put into the archive, matched to entries by file name
+        #[arg(required = true)]
+        inputs: Vec<String>,
+        /// Compress tables that are added to the archive. replaced tables keep the compression of
+        /// the entry they replace
+        #[arg(long)]
+        compress: bool,
+    },
+    /// Decompress a file compressed with the sky games' compression
+    Decompress {
+        /// Input file path for the compressed file
+        input_path: String,
+        /// Output file path for the decompressed file
+        output_path: String,
+    },
+    /// Compress a file with the sky games' compression
+    Compress {
+        /// Input file path for the file to compress
+        input_path: String,
+        /// Output file path for the compressed file
+        output_path: String,
+    },
+    /// Export the text of a t_bookXX._dt or t_item2._dt file to a gettext .po file
+    PoExport {
+        /// Input file path for the t_bookXX._dt or t_item2._dt file
+        input_path: String,
+        /// Write a .pot template instead of a .po file
+        #[arg(long)]
+        template: bool,
+    },
+    /// Apply the translations in a .po file to a t_bookXX._dt or t_item2._dt file
+    PoImport {
+        /// Input file path for the t_bookXX._dt or t_item2._dt file the .po was exported from
+        input_path: String,
+        /// Path to the translated .po file
+        po_path: String,
+        /// Output file path for the translated table
+        #[arg(long)]
+        output: String,
+    },
+    /// Export the text of a t_bookXX._dt or t_item2._dt file to an XLIFF 2.0 file
+    XliffExport {
+        /// Input file path for the t_bookXX._dt or t_item2._dt file
+        input_path: String,
+        /// Language of the table's text
+        #[arg(long, default_value = "ja")]
+        source_language: String,
+        /// Language the text is translated into
+        #[arg(long, default_value = "en")]
+        target_language: String,
+    },
+    /// Apply the translations in an XLIFF 2.0 file to a t_bookXX._dt or t_item2._dt file
+    XliffImport {
+        /// Input file path for the t_bookXX._dt or t_item2._dt file the XLIFF was exported from
+        input_path: String,
+        /// Path to the translated XLIFF file
+        xliff_path: String,
+        /// Output file path for the translated table
+        #[arg(long)]
+        output: String,
+    },
+    /// Export the text of a t_bookXX._dt or t_item2._dt file to a CSV file for proofreading
+    SheetExport {
+        /// Input file path for the t_bookXX._dt or t_item2._dt file
+        input_path: String,
+        /// Write a TSV file instead of a CSV file
+        #[arg(long)]
+        tsv: bool,
+    },
+    /// Apply the edited text cells of a CSV or TSV file to a t_bookXX._dt or t_item2._dt file
+    SheetImport {
+        /// Input file path for the t_bookXX._dt or t_item2._dt file the sheet was exported from
+        input_path: String,
+        /// Path to the edited CSV or TSV file
+        sheet_path: String,
+        /// Output file path for the updated table
+        #[arg(long)]
+        output: String,
+    },
+    /// Line up the text of two decodings of the same t_bookXX or t_item2 table side by side
+    Align {
+        /// Path to the source side, a ._dt file or its json
+        source_path: String,
+        /// Path to the target side, a ._dt file or its json
+        target_path: String,
+        /// Format of the bilingual file
+        #[arg(long, value_enum, default_value = "json")]
+        format: align::Format,
+    },
+    /// Report the text that differs between two versions of a t_bookXX or t_item2 table
+    Diff {
+        /// Path to the old version, a ._dt file or its json
+        old_path: String,
+        /// Path to the new version, a ._dt file or its json
+        new_path: String,
+    },
 }
 
 pub fn run() {
     let cli = Cli::parse();
+    if let Some(game) = cli.game {
+        game::select(game);
+    }
+
+    if let Err(e) = config::load() {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+    encoding::select(cli.encoding.or(config::get().encoding).unwrap_or_default());
+    if let Some(path) = cli.glyphs.or_else(|| config::get().glyphs.clone())
+        && let Err(e) = glyphs::load(&path)
+    {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+    if cli.normalize || config::get().normalize {
+        normalize::enable();
+    }
 
     if let Some(command) = cli.command {
         match command {
-            Commands::TBookToJson { input_path } => {
-                run_function(t_book::convert_t_book_to_json_file, input_path);
+            Commands::TBookToJson { input_path, faces } => {
+                run_function(
+                    |path| t_book::convert_t_book_to_json_file(path, faces),
+                    input_path,
+                );
             }
             Commands::TItem2ToJson { input_path } => {
                 run_function(t_item2::convert_t_items2_to_json_file, input_path);
@@ -52,6 +353,175 @@ pub fn run() {
             Commands::JsonToTItem2 { input_path } => {
                 run_function(t_item2::convert_json_to_t_items2, input_path);
             }
+            Commands::TTownToJson { input_path } => {
+                run_function(t_town::convert_t_town_to_json_file, input_path);
+            }
+            Commands::JsonToTTown { input_path } => {
+                run_function(t_town::convert_json_to_t_town, input_path);
+            }
+            Commands::TNameToJson { input_path } => {
+                run_function(t_name::convert_t_name_to_json_file, input_path);
+            }
+            Commands::JsonToTName { input_path } => {
+                run_function(t_name::convert_json_to_t_name, input_path);
+            }
+            Commands::TStatusToJson { input_path } => {
+                run_function(t_status::convert_t_status_to_json_file, input_path);
+            }
+            Commands::JsonToTStatus { input_path } => {
+                run_function(t_status::convert_json_to_t_status, input_path);
+            }
+            Commands::TStatusToCsv { input_path } => {
+                run_function(t_status::convert_t_status_to_csv_file, input_path);
+            }
+            Commands::CsvToTStatus { input_path } => {
+                run_function(t_status::convert_csv_to_t_status, input_path);
+            }
+            Commands::TSltgetToJson { input_path } => {
+                run_function(t_sltget::convert_t_sltget_to_json_file, input_path);
+            }
+            Commands::JsonToTSltget { input_path } => {
+                run_function(t_sltget::convert_json_to_t_sltget, input_path);
+            }
+            Commands::TCookToJson { input_path, items } => {
+                run_function(
+                    |path| t_cook::convert_t_cook_to_json_file(path, items),
+                    input_path,
+                );
+            }
+            Commands::JsonToTCook { input_path } => {
+                run_function(t_cook::convert_json_to_t_cook, input_path);
+            }
+            Commands::TQuestToJson { input_path } => {
+                run_function(t_quest::convert_t_quest_to_json_file, input_path);
+            }
+            Commands::JsonToTQuest { input_path } => {
+                run_function(t_quest::convert_json_to_t_quest, input_path);
+            }
+            Commands::TFaceToJson { input_path } => {
+                run_function(t_face::convert_t_face_to_json_file, input_path);
+            }
+            Commands::JsonToTFace { input_path } => {
+                run_function(t_face::convert_json_to_t_face, input_path);
+            }
+            Commands::TBgmtblToJson { input_path } => {
+                run_function(t_bgmtbl::convert_t_bgmtbl_to_json_file, input_path);
+            }
+            Commands::JsonToTBgmtbl { input_path } => {
+                run_function(t_bgmtbl::convert_json_to_t_bgmtbl, input_path);
+            }
+            Commands::DtToJson { input_path, schema } => {
+                run_function(
+                    |path| schema::convert_dt_to_json_file(path, schema),
+                    input_path,
+                );
+            }
+            Commands::JsonToDt { input_path, schema } => {
+                run_function(|path| schema::convert_json_to_dt(path, schema), input_path);
+            }
+            Commands::RawToJson { input_path } => {
+                run_function(raw::convert_raw_to_json_file, input_path);
+            }
+            Commands::JsonToRaw { input_path } => {
+                run_function(raw::convert_json_to_raw, input_path);
+            }
+            Commands::Inspect { input_path } => {
+                run_function(inspect::inspect_file, input_path);
+            }
+            Commands::ArchiveList { dir_path } => {
+                run_function(archive::list_archive, dir_path);
+            }
+            Commands::ArchiveToJson { dir_path, name } => {
+                run_function(
+                    |path| archive::convert_archived_table_to_json_file(path, name),
+                    dir_path,
+                );
+            }
+            Commands::ArchivePack {
+                dir_path,
+                output,
+                inputs,
+                compress,
+            } => {
+                run_function(
+                    |path| archive::pack_archive(path, output, inputs, compress),
+                    dir_path,
+                );
+            }
+            Commands::Decompress {
+                input_path,
+                output_path,
+            } => {
+                run_function(
+                    |path| compression::decompress_file(path, output_path),
+                    input_path,
+                );
+            }
+            Commands::Compress {
+                input_path,
+                output_path,
+            } => {
+                run_function(
+                    |path| compression::compress_file(path, output_path),
+                    input_path,
+                );
+            }
+            Commands::PoExport {
+                input_path,
+                template,
+            } => {
+                run_function(|path| po::export_po(path, template), input_path);
+            }
+            Commands::PoImport {
+                input_path,
+                po_path,
+                output,
+            } => {
+                run_function(|path| po::import_po(path, po_path, output), input_path);
+            }
+            Commands::XliffExport {
+                input_path,
+                source_language,
+                target_language,
+            } => {
+                run_function(
+                    |path| xliff::export_xliff(path, source_language, target_language),
+                    input_path,
+                );
+            }
+            Commands::XliffImport {
+                input_path,
+                xliff_path,
+                output,
+            } => {
+                run_function(
+                    |path| xliff::import_xliff(path, xliff_path, output),
+                    input_path,
+                );
+            }
+            Commands::SheetExport { input_path, tsv } => {
+                run_function(|path| sheet::export_sheet(path, tsv), input_path);
+            }
+            Commands::SheetImport {
+                input_path,
+                sheet_path,
+                output,
+            } => {
+                run_function(
+                    |path| sheet::import_sheet(path, sheet_path, output),
+                    input_path,
+                );
+            }
+            Commands::Align {
+                source_path,
+                target_path,
+                format,
+            } => {
+                run_function(|path| align::align(path, target_path, format), source_path);
+            }
+            Commands::Diff { old_path, new_path } => {
+                run_function(|path| diff::diff(path, new_path), old_path);
+            }
         }
     } else {
         Cli::command().print_help().unwrap();
@@ -65,7 +535,11 @@ where
     F: FnOnce(String) -> Result<T, E>,
     E: std::error::Error + Display,
 {
-    if let Err(e) = func(path) {
+    game::detect(&path);
+    let result = func(path);
+    //report what was replaced even if encoding failed, the replacements may be the cause
+    normalize::report();
+    if let Err(e) = result {
         eprintln!("Error: {}", e);
         std::process::exit(1);
     }
@@ -0,0 +1,428 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+
+//compressed files in the sky archives are a series of chunks. each chunk is a u16 length, that many
+//bytes of compressed data and a u8 that is 1 if another chunk follows and 0 after the last one.
+//chunks are independent of each other and decompress to at most CHUNK_LENGTH bytes.
+//
+//this chunk layout hasn't been checked against compressed files from the games yet, only against
+//itself. in particular it assumes a chunk's length doesn't count the two bytes of the length or the
+//continuation byte, so if decompressing a real file fails with "Compressed chunk is longer than the
+//data" or "missing its continuation flag", that convention is the first thing to check.
+//
+//the compressed data mixes bytes with flag bits. the bits are read lowest first from u16 flag words,
+//and a new flag word is read from the data whenever the previous one runs out. every token starts
+//with flag bits:
+//  0          a literal byte follows
+//  1 0        a back reference: an offset byte and a count
+//  1 1        13 bits (5 flag bits, then a byte) follow. 0 ends the chunk, 1 is a run of one byte
+//             repeated (4 or 12 bits of count plus 14 and the byte), anything else is the offset
+//             of a back reference followed by a count
+//counts take 2 to 5 as 1, 01, 001 or 0001, 6 to 13 as 00001 plus 3 bits, and otherwise 00000
+//followed by a byte holding the count minus 14
+const CHUNK_LENGTH: usize = 0x7FF0;
+const WINDOW: usize = 0x1FFF;
+const MAX_COUNT: usize = 14 + 0xFF;
+const MAX_RUN: usize = 14 + 0xFFF;
+//how many earlier positions are tried when looking for a back reference
+const MAX_CANDIDATES: usize = 256;
+
+pub fn decompress_file(path: String, output_path: String) -> io::Result<()> {
+    let data = decompress(&fs::read(&path)?)?;
+    fs::write(output_path, data)
+}
+
+pub fn compress_file(path: String, output_path: String) -> io::Result<()> {
+    let data = compress(&fs::read(&path)?);
+    fs::write(output_path, data)
+}
+
+//whether the chunk lengths of the data add up exactly to its length, which plain tables are very
+//unlikely to do
+pub fn is_compressed(data: &[u8]) -> bool {
+    let mut position = 0;
+    loop {
+        if position + 2 > data.len() {
+            return false;
+        }
+        position += 2 + u16::from_le_bytes([data[position], data[position + 1]]) as usize;
+        match data.get(position) {
+            Some(0) => return position + 1 == data.len(),
+            Some(1) => position += 1,
+            _ => return false,
+        }
+    }
+}
+
+//NOTE: code for decompressing---------------------------------------------------------------------
+pub fn decompress(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut output = Vec::new();
+    let mut position = 0;
+
+    loop {
+        let length = data
+            .get(position..position + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+            .ok_or_else(|| invalid("Compressed data ends before a chunk"))?;
+        let chunk = data
+            .get(position + 2..position + 2 + length)
+            .ok_or_else(|| invalid("Compressed chunk is longer than the data"))?;
+        output.extend(decompress_chunk(chunk)?);
+        position += 2 + length;
+
+        match data.get(position) {
+            Some(0) => return Ok(output),
+            Some(1) => position += 1,
+            _ => return Err(invalid("Compressed chunk is missing its continuation flag")),
+        }
+    }
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    flags: u16,
+    flags_left: u32,
+}
+
+impl BitReader<'_> {
+    fn byte(&mut self) -> io::Result<u8> {
+        let byte = *self
+            .data
+            .get(self.position)
+            .ok_or_else(|| invalid("Compressed chunk ends in the middle of a token"))?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn bit(&mut self) -> io::Result<bool> {
+        if self.flags_left == 0 {
+            self.flags = u16::from_le_bytes([self.byte()?, self.byte()?]);
+            self.flags_left = 16;
+        }
+        let bit = self.flags & 1 == 1;
+        self.flags >>= 1;
+        self.flags_left -= 1;
+        Ok(bit)
+    }
+
+    fn bits(&mut self, count: u32) -> io::Result<usize> {
+        let mut value = 0;
+        for _ in 0..count {
+            value = value << 1 | self.bit()? as usize;
+        }
+        Ok(value)
+    }
+
+    fn count(&mut self) -> io::Result<usize> {
+        for count in 2..6 {
+            if self.bit()? {
+                return Ok(count);
+            }
+        }
+        if self.bit()? {
+            return Ok(6 + self.bits(3)?);
+        }
+        Ok(14 + self.byte()? as usize)
+    }
+}
+
+fn decompress_chunk(chunk: &[u8]) -> io::Result<Vec<u8>> {
+    let mut reader = BitReader {
+        data: chunk,
+        position: 0,
+        flags: 0,
+        flags_left: 0,
+    };
+    let mut output: Vec<u8> = Vec::new();
+
+    loop {
+        if !reader.bit()? {
+            output.push(reader.byte()?);
+            continue;
+        }
+
+        let offset = if !reader.bit()? {
+            reader.byte()? as usize
+        } else {
+            let offset = reader.bits(5)? << 8 | reader.byte()? as usize;
+            match offset {
+                0 => return Ok(output),
+                1 => {
+                    let count = if reader.bit()? {
+                        reader.bits(4)? << 8 | reader.byte()? as usize
+                    } else {
+                        reader.bits(4)?
+                    };
+                    let byte = reader.byte()?;
+                    output.extend(std::iter::repeat_n(byte, count + 14));
+                    continue;
+                }
+                offset => offset,
+            }
+        };
+
+        let count = reader.count()?;
+        if offset == 0 || offset > output.len() {
+            return Err(invalid("Compressed chunk refers back past its start"));
+        }
+        //the copy can overlap the bytes it produces, so it has to go byte by byte
+        let start = output.len() - offset;
+        for i in start..start + count {
+            output.push(output[i]);
+        }
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+//NOTE: code for compressing-----------------------------------------------------------------------
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut chunks = data.chunks(CHUNK_LENGTH).peekable();
+
+    //even empty data gets one chunk, so that it decompresses to nothing
+    if chunks.peek().is_none() {
+        write_chunk(&mut output, &compress_chunk(&[]), false);
+    }
+    while let Some(chunk) = chunks.next() {
+        write_chunk(&mut output, &compress_chunk(chunk), chunks.peek().is_some());
+    }
+
+    output
+}
+
+fn write_chunk(output: &mut Vec<u8>, chunk: &[u8], more: bool) {
+    output.extend((chunk.len() as u16).to_le_bytes());
+    output.extend(chunk);
+    output.push(more as u8);
+}
+
+struct BitWriter {
+    output: Vec<u8>,
+    //where the flag word currently being filled sits in the output
+    flags_position: usize,
+    flags_used: u32,
+}
+
+impl BitWriter {
+    fn byte(&mut self, byte: u8) {
+        self.output.push(byte);
+    }
+
+    fn bit(&mut self, bit: bool) {
+        //the reader fetches a new flag word at the point it runs out, so the word is reserved here
+        if self.flags_used == 16 {
+            self.flags_position = self.output.len();
+            self.output.extend([0, 0]);
+            self.flags_used = 0;
+        }
+        if bit {
+            self.output[self.flags_position + (self.flags_used / 8) as usize] |=
+                1 << (self.flags_used % 8);
+        }
+        self.flags_used += 1;
+    }
+
+    fn bits(&mut self, count: u32, value: usize) {
+        for i in (0..count).rev() {
+            self.bit(value >> i & 1 == 1);
+        }
+    }
+
+    fn count(&mut self, count: usize) {
+        match count {
+            2..=5 => {
+                self.bits(count as u32 - 2, 0);
+                self.bit(true);
+            }
+            6..=13 => {
+                self.bits(5, 1);
+                self.bits(3, count - 6);
+            }
+            _ => {
+                self.bits(5, 0);
+                self.byte((count - 14) as u8);
+            }
+        }
+    }
+}
+
+fn compress_chunk(chunk: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter {
+        output: Vec::new(),
+        flags_position: 0,
+        flags_used: 16,
+    };
+    //earlier positions of every pair of bytes, most recent last
+    let mut positions: HashMap<[u8; 2], Vec<usize>> = HashMap::new();
+    let mut i = 0;
+
+    while i < chunk.len() {
+        let run = chunk[i..]
+            .iter()
+            .take(MAX_RUN)
+            .take_while(|&&b| b == chunk[i])
+            .count();
+        let (offset, count) = find_match(chunk, i, &positions);
+
+        let consumed = if run >= 14 && run > count {
+            writer.bits(2, 0b11);
+            writer.bits(5, 0);
+            writer.byte(1);
+            let extra = run - 14;
+            if extra > 0xF {
+                writer.bit(true);
+                writer.bits(4, extra >> 8);
+                writer.byte(extra as u8);
+            } else {
+                writer.bit(false);
+                writer.bits(4, extra);
+            }
+            writer.byte(chunk[i]);
+            run
+        } else if count >= 2 {
+            if offset <= 0xFF {
+                writer.bits(2, 0b10);
+                writer.byte(offset as u8);
+            } else {
+                writer.bits(2, 0b11);
+                writer.bits(5, offset >> 8);
+                writer.byte(offset as u8);
+            }
+            writer.count(count);
+            count
+        } else {
+            writer.bit(false);
+            writer.byte(chunk[i]);
+            1
+        };
+
+        for position in i..i + consumed {
+            if let Some(pair) = chunk.get(position..position + 2) {
+                positions
+                    .entry([pair[0], pair[1]])
+                    .or_default()
+                    .push(position);
+            }
+        }
+        i += consumed;
+    }
+
+    //end of chunk: a long back reference with offset 0
+    writer.bits(2, 0b11);
+    writer.bits(5, 0);
+    writer.byte(0);
+
+    writer.output
+}
+
+//the longest earlier occurrence of the bytes at i within the window, as (offset, count)
+fn find_match(chunk: &[u8], i: usize, positions: &HashMap<[u8; 2], Vec<usize>>) -> (usize, usize) {
+    let Some(candidates) = chunk
+        .get(i..i + 2)
+        .and_then(|pair| positions.get(&[pair[0], pair[1]]))
+    else {
+        return (0, 0);
+    };
+
+    let mut best = (0, 0);
+    for &start in candidates.iter().rev().take(MAX_CANDIDATES) {
+        let offset = i - start;
+        if offset > WINDOW {
+            break;
+        }
+        let count = chunk[i..]
+            .iter()
+            .take(MAX_COUNT)
+            .zip(&chunk[start..])
+            .take_while(|(a, b)| a == b)
+            .count();
+        if count > best.1 {
+            best = (offset, count);
+        }
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //"abab" assembled by hand from the format described at the top of this file: a chunk length of
+    //6, the flag word 0x0074 (literal, literal, back reference, count 2, end of chunk), the bytes of
+    //the tokens and the flag ending the last chunk. not a sample taken from the game's archives
+    const ABAB: [u8; 9] = [0x06, 0x00, 0x74, 0x00, b'a', b'b', 0x02, 0x00, 0x00];
+
+    fn round_trip(data: &[u8]) {
+        let compressed = compress(data);
+        assert!(is_compressed(&compressed));
+        assert_eq!(decompress(&compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn decompresses_hand_assembled_data() {
+        assert_eq!(decompress(&ABAB).unwrap(), b"abab");
+        assert_eq!(compress(b"abab"), ABAB);
+    }
+
+    #[test]
+    fn decompresses_runs() {
+        //a run of 20: offset 1, a short count of 20 - 14 and the repeated byte, then the end of
+        //the chunk, which runs out of the first flag word 3 bits before its offset byte
+        let data = [0x07, 0x00, 0x03, 0x36, 0x01, b'z', 0x00, 0x00, 0x00, 0x00];
+        assert_eq!(decompress(&data).unwrap(), [b'z'; 20]);
+    }
+
+    #[test]
+    fn round_trips() {
+        round_trip(b"");
+        round_trip(b"x");
+        round_trip(b"the quick brown fox jumps over the lazy dog, the quick brown fox");
+        round_trip(&[7; 40]);
+        round_trip(&[7; MAX_RUN + 100]);
+
+        //long back references and bytes that don't repeat
+        let mut state = 1u32;
+        let noise: Vec<u8> = (0..0x3000)
+            .map(|_| {
+                state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect();
+        let mut data = noise.clone();
+        data.extend(&noise[..0x1000]);
+        round_trip(&data);
+    }
+
+    #[test]
+    fn splits_long_data_into_chunks() {
+        let data: Vec<u8> = (0..CHUNK_LENGTH * 2 + 5).map(|i| (i % 251) as u8).collect();
+        let compressed = compress(&data);
+
+        let first = u16::from_le_bytes([compressed[0], compressed[1]]) as usize;
+        assert_eq!(compressed[2 + first], 1);
+        assert_eq!(compressed.last(), Some(&0));
+        assert_eq!(decompress(&compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn detects_compressed_data() {
+        assert!(is_compressed(&ABAB));
+        assert!(is_compressed(&compress(b"t_name._dt")));
+
+        //a plain table: a pointer list and two records
+        assert!(!is_compressed(&[
+            0x04, 0x00, 0x07, 0x00, b'a', b'b', 0x00, b'c', 0x00
+        ]));
+        assert!(!is_compressed(&[]));
+        assert!(!is_compressed(&ABAB[..8]));
+        let mut trailing = ABAB.to_vec();
+        trailing.push(0);
+        assert!(!is_compressed(&trailing));
+    }
+}
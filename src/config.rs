@@ -0,0 +1,51 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::encoding::Encoding;
+
+//settings for a project, read from gospel.json in the working directory if there is one. options
+//given on the command line take precedence over the config
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub encoding: Option<Encoding>,
+    //replacements for characters the encoding can't represent, e.g. "é": "e"
+    pub substitutions: BTreeMap<char, String>,
+    //path of a glyph map for fonts patched to draw extra letters, see glyphs.rs
+    pub glyphs: Option<String>,
+    //replace typographic characters before encoding, see normalize.rs
+    pub normalize: bool,
+    //changes to the default normalizations, null turns one off
+    pub normalization: BTreeMap<char, Option<String>>,
+    //bytes of item name to warn above instead of the game's estimated limit, see game.rs
+    pub item_name_limit: Option<usize>,
+}
+
+pub const CONFIG_FILE: &str = "gospel.json";
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+//read the project config, failing if it exists but isn't valid
+pub fn load() -> io::Result<()> {
+    let config = if Path::new(CONFIG_FILE).exists() {
+        serde_json::from_str(&fs::read_to_string(CONFIG_FILE)?).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid {}: {}", CONFIG_FILE, e),
+            )
+        })?
+    } else {
+        Config::default()
+    };
+
+    let _ = CONFIG.set(config);
+    Ok(())
+}
+
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
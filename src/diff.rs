@@ -0,0 +1,135 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+
+use crate::tables;
+use crate::text::{self, TextEntry};
+
+//compare the text of two versions of a book or item table, each a ._dt file or its json. text is
+//matched by its key from text.rs, so ids and formatting don't get in the way. a book, page or item
+//that is only in one of them is reported once with all of its text, and everything else line by
+//line or field by field
+pub fn diff(old_path: String, new_path: String) -> io::Result<()> {
+    let old_table = tables::table_name(&text::file_name(&old_path)?);
+    let new_table = tables::table_name(&text::file_name(&new_path)?);
+    if old_table != new_table {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} and {} are different tables, {} and {}",
+                old_path, new_path, old_table, new_table
+            ),
+        ));
+    }
+
+    let old = Side::new(&old_table, text::read_entries(&old_path)?);
+    let new = Side::new(&old_table, text::read_entries(&new_path)?);
+
+    let mut keys: Vec<&String> = old.texts.keys().chain(new.texts.keys()).collect();
+    keys.sort_by_key(|key| sort_key(key));
+    keys.dedup();
+
+    let mut differences = 0;
+    let mut reported_container = None;
+    for key in keys {
+        let segments: Vec<&str> = key.split('/').collect();
+        let labels = labels(&segments);
+        let (old_text, new_text) = (old.texts.get(key), new.texts.get(key));
+
+        //the outermost book, page or item that only one of them has
+        let added_or_removed = (1..segments.len()).find_map(|length| {
+            let container = segments[..length].join("/");
+            match (old.has(&container), new.has(&container)) {
+                (true, false) => Some((container, length, "removed")),
+                (false, true) => Some((container, length, "added")),
+                _ => None,
+            }
+        });
+        if let Some((container, length, change)) = added_or_removed {
+            if reported_container.as_ref() != Some(&container) {
+                println!("{}: {}", labels[..length].join(", "), change);
+                differences += 1;
+                reported_container = Some(container);
+            }
+            let text = old_text.or(new_text).map_or("", String::as_str);
+            println!("  {}: {:?}", labels[length..].join(", "), text);
+            continue;
+        }
+
+        let location = labels.join(", ");
+        match (old_text, new_text) {
+            (Some(old_text), Some(new_text)) if old_text != new_text => {
+                println!("{}: changed {:?} -> {:?}", location, old_text, new_text);
+            }
+            (Some(old_text), None) => println!("{}: removed {:?}", location, old_text),
+            (None, Some(new_text)) => println!("{}: added {:?}", location, new_text),
+            _ => continue,
+        }
+        differences += 1;
+    }
+
+    if differences == 0 {
+        println!("No differences");
+    } else {
+        println!("{} difference(s)", differences);
+    }
+    Ok(())
+}
+
+//the text of one of the versions by key, along with the books, pages and items it has
+struct Side {
+    texts: HashMap<String, String>,
+    containers: HashSet<String>,
+}
+
+impl Side {
+    fn new(table: &str, entries: Vec<TextEntry>) -> Self {
+        let mut side = Side {
+            texts: HashMap::new(),
+            containers: HashSet::new(),
+        };
+        for entry in entries {
+            //keys are relative to the table, e.g. "2/1/0" for line 0 of page 1 of book 2
+            let key = entry.key[table.len() + 1..].to_string();
+            let segments: Vec<&str> = key.split('/').collect();
+            for length in 1..segments.len() {
+                side.containers.insert(segments[..length].join("/"));
+            }
+            side.texts.insert(key, entry.text);
+        }
+        side
+    }
+
+    fn has(&self, container: &str) -> bool {
+        self.containers.contains(container)
+    }
+}
+
+//describe every segment of a key, e.g. "book 2", "page 1" and "line 0" for "2/1/0"
+fn labels(segments: &[&str]) -> Vec<String> {
+    let is_book = segments.len() == 3 || segments[1] == "title";
+    segments
+        .iter()
+        .enumerate()
+        .map(|(i, segment)| match (*segment, i) {
+            ("title", _) => "title".to_string(),
+            ("name", _) => "name".to_string(),
+            ("desc", _) => "description".to_string(),
+            (id, 0) if is_book => format!("book {}", id),
+            (id, 0) => format!("item {}", id),
+            (id, 1) => format!("page {}", id),
+            (id, _) => format!("line {}", id),
+        })
+        .collect()
+}
+
+//order keys by their ids as numbers, with a book's title before its pages and an item's name before
+//its description
+fn sort_key(key: &str) -> Vec<(u8, u32)> {
+    key.split('/')
+        .map(|segment| match segment {
+            "title" | "name" => (0, 0),
+            "desc" => (0, 1),
+            id => (1, id.parse().unwrap_or(u32::MAX)),
+        })
+        .collect()
+}
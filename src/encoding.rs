@@ -0,0 +1,127 @@
+use clap::ValueEnum;
+use encoding_rs::{GBK, SHIFT_JIS, UTF_8, WINDOWS_1252};
+use serde::Deserialize;
+use std::fmt;
+use std::sync::OnceLock;
+
+//the text encodings gospel can read and write table strings in. the japanese and english releases
+//use CP932, the chinese releases GBK, and fan patched engines UTF-8 or a single byte latin encoding.
+//the encoding is picked once per run from --encoding, the project config or the CP932 default and
+//used by every table through util::decode_string and util::encode_string
+#[derive(Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize)]
+pub enum Encoding {
+    #[default]
+    #[value(name = "cp932")]
+    #[serde(rename = "cp932")]
+    Cp932,
+    #[value(name = "gbk")]
+    #[serde(rename = "gbk")]
+    Gbk,
+    #[value(name = "utf-8")]
+    #[serde(rename = "utf-8")]
+    Utf8,
+    //every byte is the unicode character of the same number
+    #[value(name = "latin-1")]
+    #[serde(rename = "latin-1")]
+    Latin1,
+    #[value(name = "windows-1252")]
+    #[serde(rename = "windows-1252")]
+    Windows1252,
+}
+
+static ENCODING: OnceLock<Encoding> = OnceLock::new();
+
+impl Encoding {
+    //decode the character at the start of bytes, returning it along with the number of bytes it
+    //takes up, or None if the bytes there aren't a valid character
+    pub fn decode_char(self, bytes: &[u8]) -> Option<(char, usize)> {
+        let first = *bytes.first()?;
+        let length = match self {
+            Encoding::Cp932 if matches!(first, 0x81..=0x9F | 0xE0..=0xFC) => 2,
+            Encoding::Gbk if matches!(first, 0x81..=0xFE) => 2,
+            Encoding::Utf8 if first >= 0xC0 => first.leading_ones() as usize,
+            Encoding::Latin1 => return Some((first as char, 1)),
+            _ => 1,
+        };
+
+        let decoded = self
+            .codec()?
+            .decode_without_bom_handling_and_without_replacement(bytes.get(..length)?)?;
+        let mut chars = decoded.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some((c, length)),
+            _ => None,
+        }
+    }
+
+    //encode a single character, or None if the encoding has no bytes for it
+    pub fn encode_char(self, c: char) -> Option<Vec<u8>> {
+        let Some(codec) = self.codec() else {
+            return u8::try_from(c).ok().map(|b| vec![b]);
+        };
+        let mut buffer = [0u8; 4];
+        let (bytes, _, error) = codec.encode(c.encode_utf8(&mut buffer));
+        (!error).then(|| bytes.into_owned())
+    }
+
+    //the encoding_rs codec behind the encoding. latin-1 isn't one, encoding_rs treats it as
+    //windows-1252
+    fn codec(self) -> Option<&'static encoding_rs::Encoding> {
+        match self {
+            Encoding::Cp932 => Some(SHIFT_JIS),
+            Encoding::Gbk => Some(GBK),
+            Encoding::Utf8 => Some(UTF_8),
+            Encoding::Windows1252 => Some(WINDOWS_1252),
+            Encoding::Latin1 => None,
+        }
+    }
+
+    //the length of the printable character starting at bytes[i], or 0 if there is none. used to
+    //find strings in tables gospel doesn't know the layout of
+    pub fn printable_width(self, bytes: &[u8], i: usize) -> usize {
+        let next = bytes.get(i + 1).copied();
+        match (self, bytes[i]) {
+            (_, 0x20..=0x7E) => 1,
+            (Encoding::Cp932, 0xA1..=0xDF) => 1,
+            (Encoding::Cp932, 0x81..=0x9F | 0xE0..=0xFC)
+                if matches!(next, Some(0x40..=0x7E | 0x80..=0xFC)) =>
+            {
+                2
+            }
+            (Encoding::Gbk, 0x81..=0xFE) if matches!(next, Some(0x40..=0x7E | 0x80..=0xFE)) => 2,
+            (Encoding::Utf8, 0xC2..=0xF4) => {
+                let length = bytes[i].leading_ones() as usize;
+                bytes
+                    .get(i..i + length)
+                    .and_then(|c| std::str::from_utf8(c).ok())
+                    .map_or(0, |_| length)
+            }
+            (Encoding::Latin1, 0xA0..=0xFF) => 1,
+            //0x81, 0x8D, 0x8F, 0x90 and 0x9D are unassigned
+            (Encoding::Windows1252, 0x80 | 0x82..=0x8C | 0x8E | 0x91..=0x9C | 0x9E..=0xFF) => 1,
+            _ => 0,
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Encoding::Cp932 => "CP932",
+            Encoding::Gbk => "GBK",
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Latin1 => "Latin-1",
+            Encoding::Windows1252 => "Windows-1252",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+//use the given encoding for the rest of the run
+pub fn select(encoding: Encoding) {
+    let _ = ENCODING.set(encoding);
+}
+
+pub fn current() -> Encoding {
+    *ENCODING.get().unwrap_or(&Encoding::Cp932)
+}
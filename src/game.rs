@@ -0,0 +1,144 @@
+use clap::ValueEnum;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::config;
+use crate::tables::{self, t_item2};
+
+//the games of the sky trilogy. tables that differ between them look up the game being worked on
+//through current(), which is picked once per run from --game or the paths given to the command
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Game {
+    #[value(name = "fc")]
+    Fc,
+    #[value(name = "sc")]
+    Sc,
+    #[value(name = "3rd")]
+    Third,
+}
+
+static GAME: OnceLock<Game> = OnceLock::new();
+
+impl Game {
+    //SC and the 3rd store an id at the start of every item record, FC only has the text pointers
+    pub fn has_item_ids(self) -> bool {
+        self != Game::Fc
+    }
+
+    //roughly how many bytes of item name the game's menus have room for. these are estimates rather
+    //than widths taken from the games' code, so longer names are only warned about and a project can
+    //set its own limit with item_name_limit in gospel.json
+    pub fn item_name_limit(self) -> usize {
+        config::get().item_name_limit.unwrap_or(match self {
+            Game::Fc => 24,
+            Game::Sc | Game::Third => 32,
+        })
+    }
+
+    //whether the game ships the given table, e.g. "t_quest". the 3rd has no bracer guild and so no
+    //quest table
+    pub fn has_table(self, table: &str) -> bool {
+        !(self == Game::Third && table == "t_quest")
+    }
+}
+
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Game::Fc => "FC",
+            Game::Sc => "SC",
+            Game::Third => "the 3rd",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+//use the game given with --game for the rest of the run
+pub fn select(game: Game) {
+    let _ = GAME.set(game);
+}
+
+//pick the game from a path given to the command unless one was selected already. if the path
+//doesn't tell, the layout of the table it leads to might, and FC is assumed otherwise
+pub fn detect(path: &str) {
+    GAME.get_or_init(|| {
+        if let Some(game) = detect_from_path(path) {
+            eprintln!("Detected {} from {}, pass --game to override", game, path);
+            return game;
+        }
+        match detect_from_layout(path) {
+            Some(Game::Fc) => {
+                eprintln!(
+                    "Detected FC from the layout of {}, pass --game to override",
+                    path
+                );
+                Game::Fc
+            }
+            //SC and the 3rd share the layouts this can tell apart
+            Some(_) => {
+                eprintln!(
+                    "Detected SC or the 3rd from the layout of {}, pass --game to override",
+                    path
+                );
+                Game::Sc
+            }
+            None => Game::Fc,
+        }
+    });
+}
+
+pub fn current() -> Game {
+    *GAME.get().unwrap_or(&Game::Fc)
+}
+
+//fail when the current game doesn't have a table
+pub fn check_table(table: &str) -> io::Result<()> {
+    if !current().has_table(table) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} doesn't have a {} table", current(), table),
+        ));
+    }
+    Ok(())
+}
+
+//the archives of each game are numbered by game: ED6_DT0x and ED6_DT1x belong to FC, ED6_DT2x to
+//SC and ED6_DT3x to the 3rd. this also works for tables extracted into a folder named after their
+//archive
+fn detect_from_path(path: &str) -> Option<Game> {
+    //relative paths are resolved so that working inside an extracted archive folder counts too
+    let path = std::fs::canonicalize(path)
+        .map_or_else(|_| path.to_string(), |p| p.to_string_lossy().into_owned())
+        .to_ascii_lowercase()
+        .replace('\\', "/");
+    path.split('/').rev().find_map(|component| {
+        let digit = component.strip_prefix("ed6_dt")?.chars().next()?;
+        match digit {
+            '0' | '1' => Some(Game::Fc),
+            '2' => Some(Game::Sc),
+            '3' => Some(Game::Third),
+            _ => None,
+        }
+    })
+}
+
+//the item table is the only table whose layout differs between the games, as FC doesn't store item
+//ids
+fn detect_from_layout(path: &str) -> Option<Game> {
+    let file_name = Path::new(path)
+        .file_name()?
+        .to_string_lossy()
+        .to_ascii_lowercase();
+    if !file_name.ends_with("._dt") || tables::table_name(&file_name) != "t_item2" {
+        return None;
+    }
+
+    let data = fs::read(path).ok()?;
+    match t_item2::stores_item_ids(&data)? {
+        true => Some(Game::Sc),
+        false => Some(Game::Fc),
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::sync::OnceLock;
+
+use crate::util;
+
+//fan translations patch the game's font to draw letters the encoding doesn't have, like é or ß, in
+//place of code points the game never uses. a glyph map tells gospel about those, so that the json
+//has the real letters while the tables get the remapped code points. it is a json object from
+//characters to the bytes they are stored as in hex:
+//
+//{
+//  "é": "8540",
+//  "ß": "8541"
+//}
+//
+//the map is applied on top of the selected encoding in both directions and takes precedence over it
+struct GlyphMap {
+    bytes: HashMap<char, Vec<u8>>,
+    //longest byte sequences first, so that a sequence is never cut short by one of its prefixes
+    chars: Vec<(Vec<u8>, char)>,
+}
+
+static GLYPHS: OnceLock<GlyphMap> = OnceLock::new();
+
+//read a glyph map and use it for the rest of the run
+pub fn load(path: &str) -> io::Result<()> {
+    let invalid = |message: String| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid glyph map {}: {}", path, message),
+        )
+    };
+
+    let entries: BTreeMap<char, String> =
+        serde_json::from_str(&fs::read_to_string(path)?).map_err(|e| invalid(e.to_string()))?;
+
+    let mut map = GlyphMap {
+        bytes: HashMap::new(),
+        chars: Vec::new(),
+    };
+    for (c, hex) in entries {
+        let bytes = util::from_hex(&hex).map_err(|e| invalid(format!("'{}': {}", c, e)))?;
+        //a null byte would end the string it is written into
+        if bytes.is_empty() || bytes.contains(&0) {
+            return Err(invalid(format!(
+                "'{}' must map to bytes other than 0, not \"{}\"",
+                c, hex
+            )));
+        }
+        if let Some((_, other)) = map.chars.iter().find(|(b, _)| *b == bytes) {
+            return Err(invalid(format!(
+                "'{}' and '{}' both map to {}",
+                other, c, hex
+            )));
+        }
+
+        map.chars.push((bytes.clone(), c));
+        map.bytes.insert(c, bytes);
+    }
+    map.chars
+        .sort_by_key(|(bytes, _)| std::cmp::Reverse(bytes.len()));
+
+    let _ = GLYPHS.set(map);
+    Ok(())
+}
+
+//the bytes a character is remapped to, if it is in the glyph map
+pub fn encode(c: char) -> Option<Vec<u8>> {
+    GLYPHS.get()?.bytes.get(&c).cloned()
+}
+
+//the remapped character at the start of bytes along with the number of bytes it takes up, if they
+//start with one
+pub fn decode(bytes: &[u8]) -> Option<(char, usize)> {
+    GLYPHS
+        .get()?
+        .chars
+        .iter()
+        .find(|(sequence, _)| bytes.starts_with(sequence))
+        .map(|(sequence, c)| (*c, sequence.len()))
+}
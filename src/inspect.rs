@@ -0,0 +1,206 @@
+use std::fs;
+use std::io;
+
+use crate::raw;
+use crate::util;
+
+//how many bytes are shown on one line of the dump
+const BYTES_PER_LINE: usize = 16;
+
+//a run of bytes within a record along with what gospel thinks it is
+struct Token {
+    offset: usize,
+    length: usize,
+    annotation: String,
+}
+
+//print the structure of a _dt file: its pointer list, where each record starts and how long it is,
+//and a dump of every record with text and control codes annotated next to the bytes they came from
+pub fn inspect_file(path: String) -> io::Result<()> {
+    let data = fs::read(&path)?;
+    println!("{}: {} bytes", path, data.len());
+
+    let (addresses, starts) = match raw::find_records(&data) {
+        Ok(records) => records,
+        Err(e) => {
+            //still show what's there, as one region covering the whole file
+            println!("No pointer list found ({})", e);
+            println!();
+            print_region(&data, 0, data.len())?;
+            return Ok(());
+        }
+    };
+
+    println!();
+    println!(
+        "Pointer list: {:#06X}-{:#06X}, {} pointers",
+        0,
+        addresses.len() * 2 - 1,
+        addresses.len()
+    );
+    for (i, address) in addresses.iter().enumerate() {
+        let record = starts.binary_search(address).unwrap();
+        println!(
+            "  [{}] at {:#06X} -> {:#06X} (record {})",
+            i,
+            i * 2,
+            address,
+            record
+        );
+    }
+
+    for (i, &start) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).map_or(data.len(), |&a| a as usize);
+        println!();
+        println!(
+            "Record {} at {:#06X}, {} bytes",
+            i,
+            start,
+            end - start as usize
+        );
+        print_region(&data, start as usize, end)?;
+    }
+
+    Ok(())
+}
+
+fn print_region(data: &[u8], start: usize, end: usize) -> io::Result<()> {
+    for token in tokenize(&data[start..end])? {
+        let offset = start + token.offset;
+        let bytes = &data[offset..offset + token.length];
+
+        //long tokens wrap onto several lines, with the annotation on the first one
+        for (line, chunk) in bytes.chunks(BYTES_PER_LINE).enumerate() {
+            let hex: Vec<String> = chunk.iter().map(|b| format!("{:02X}", b)).collect();
+            let annotation = if line == 0 {
+                token.annotation.as_str()
+            } else {
+                ""
+            };
+            println!(
+                "  {:#06X}  {:<width$}  {}",
+                offset + line * BYTES_PER_LINE,
+                hex.join(" "),
+                annotation,
+                width = BYTES_PER_LINE * 3 - 1
+            );
+        }
+    }
+
+    Ok(())
+}
+
+//split a record into text runs, control codes and whatever is left over
+fn tokenize(bytes: &[u8]) -> io::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut unknown_start = None;
+    //control codes are only picked out right after text, so that binary data isn't shown as a
+    //string of them
+    let mut in_text = false;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let token = match read_text(bytes, i, in_text)? {
+            Some(token) => Some(token),
+            None => read_control_code(bytes, i).filter(|_| in_text || bytes[i] == 0x23),
+        };
+
+        match token {
+            Some(token) => {
+                if let Some(start) = unknown_start.take() {
+                    tokens.push(unknown(start, i));
+                }
+                //the end of a string is also the end of the text
+                in_text = bytes[i] != 0x00;
+                i += token.length;
+                tokens.push(token);
+            }
+            None => {
+                unknown_start.get_or_insert(i);
+                in_text = false;
+                i += 1;
+            }
+        }
+    }
+    if let Some(start) = unknown_start {
+        tokens.push(unknown(start, bytes.len()));
+    }
+
+    Ok(tokens)
+}
+
+//a run of printable characters starting at i. outside of text it takes at least two characters to
+//count as text
+fn read_text(bytes: &[u8], i: usize, in_text: bool) -> io::Result<Option<Token>> {
+    let mut end = i;
+    let mut characters = 0;
+    while end < bytes.len() {
+        let width = raw::printable_width(bytes, end);
+        if width == 0 {
+            break;
+        }
+        //leave formatting codes to read_control_code
+        if bytes[end] == 0x23 && read_control_code(bytes, end).is_some() {
+            break;
+        }
+        end += width;
+        characters += 1;
+    }
+
+    if characters < if in_text { 1 } else { 2 } {
+        return Ok(None);
+    }
+
+    let text = util::decode_string(&bytes[i..end])?;
+    Ok(Some(Token {
+        offset: i,
+        length: end - i,
+        annotation: format!("{:?}", text),
+    }))
+}
+
+//the control codes used by book and quest text
+fn read_control_code(bytes: &[u8], i: usize) -> Option<Token> {
+    let (length, annotation) = match bytes[i] {
+        0x00 => (1, "<end>".to_string()),
+        0x01 => (1, "<line break>".to_string()),
+        0x02 => (1, "<wait for input>".to_string()),
+        0x03 => (1, "<end of page>".to_string()),
+        0x07 if i + 1 < bytes.len() => (2, format!("<color {}>", bytes[i + 1])),
+        //formatting codes are # followed by a number and a letter saying what the number is for
+        0x23 => {
+            let digits = bytes[i + 1..]
+                .iter()
+                .take_while(|b| b.is_ascii_digit())
+                .count();
+            let kind = match bytes.get(i + 1 + digits) {
+                Some(b'x') => "image x",
+                Some(b'y') => "image y",
+                Some(b'F') if digits == 0 => "clear face",
+                Some(b'F') => "face",
+                Some(b'S') => "text size",
+                _ => return None,
+            };
+            let value = String::from_utf8_lossy(&bytes[i + 1..i + 1 + digits]);
+            (
+                digits + 2,
+                format!("<{} {}>", kind, value).replace(" >", ">"),
+            )
+        }
+        _ => return None,
+    };
+
+    Some(Token {
+        offset: i,
+        length,
+        annotation,
+    })
+}
+
+fn unknown(start: usize, end: usize) -> Token {
+    Token {
+        offset: start,
+        length: end - start,
+        annotation: String::new(),
+    }
+}
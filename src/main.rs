@@ -1,6 +1,22 @@
+mod align;
+mod archive;
 mod command;
+mod compression;
+mod config;
+mod diff;
+mod encoding;
+mod game;
+mod glyphs;
+mod inspect;
+mod normalize;
+mod po;
+mod raw;
+mod schema;
+mod sheet;
 mod tables;
+mod text;
 pub mod util;
+mod xliff;
 
 fn main() {
     command::run();
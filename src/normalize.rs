@@ -0,0 +1,97 @@
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+
+use crate::config;
+
+//translators paste typographic characters that CP932 either lacks or only has as full width glyphs
+//that look out of place next to the game's half width text. when enabled with --normalize or
+//"normalize": true in gospel.json, these are replaced in edited text (json being encoded and
+//imported translations) before it is encoded. tables being decoded are never normalized, so the
+//check that they encode back to the same bytes isn't affected. the config can change or add
+//replacements with "normalization", and turn a default off by setting it to null
+const DEFAULTS: &[(char, &str)] = &[
+    ('\u{2018}', "'"),   //left single quote
+    ('\u{2019}', "'"),   //right single quote
+    ('\u{201A}', "'"),   //low single quote
+    ('\u{201C}', "\""),  //left double quote
+    ('\u{201D}', "\""),  //right double quote
+    ('\u{201E}', "\""),  //low double quote
+    ('\u{2013}', "-"),   //en dash
+    ('\u{2014}', "-"),   //em dash
+    ('\u{2026}', "..."), //ellipsis
+    ('\u{00A0}', " "),   //non-breaking space
+    ('\u{202F}', " "),   //narrow non-breaking space
+];
+
+static TABLE: OnceLock<BTreeMap<char, String>> = OnceLock::new();
+
+//every substitution made so far, counted along with the first text it was made in
+static REPORT: Mutex<BTreeMap<char, (usize, String)>> = Mutex::new(BTreeMap::new());
+
+//turn normalization on for the rest of the run, using the defaults as changed by the project config
+pub fn enable() {
+    TABLE.get_or_init(|| {
+        let mut table: BTreeMap<char, String> = DEFAULTS
+            .iter()
+            .map(|&(c, replacement)| (c, replacement.to_string()))
+            .collect();
+        for (c, replacement) in &config::get().normalization {
+            match replacement {
+                Some(replacement) => table.insert(*c, replacement.clone()),
+                None => table.remove(c),
+            };
+        }
+        table
+    });
+}
+
+pub fn enabled() -> bool {
+    TABLE.get().is_some()
+}
+
+//the text with every character that has a replacement replaced, recording the replacements for the
+//report. the text is returned as it is if normalization is off
+pub fn normalize(text: &str) -> String {
+    let Some(table) = TABLE.get() else {
+        return text.to_string();
+    };
+
+    let mut normalized = String::with_capacity(text.len());
+    for c in text.chars() {
+        match table.get(&c) {
+            Some(replacement) => {
+                let mut report = REPORT.lock().unwrap();
+                report.entry(c).or_insert_with(|| (0, text.to_string())).0 += 1;
+                normalized.push_str(replacement);
+            }
+            None => normalized.push(c),
+        }
+    }
+
+    normalized
+}
+
+//normalize every string of a json document
+pub fn normalize_json(value: &mut Value) {
+    match value {
+        Value::String(text) => *text = normalize(text),
+        Value::Array(values) => values.iter_mut().for_each(normalize_json),
+        Value::Object(map) => map.values_mut().for_each(normalize_json),
+        _ => {}
+    }
+}
+
+//print every substitution made during the run so they can be reviewed
+pub fn report() {
+    let (Some(table), Ok(report)) = (TABLE.get(), REPORT.lock()) else {
+        return;
+    };
+
+    for (c, (count, example)) in report.iter() {
+        eprintln!(
+            "Normalized '{}' (U+{:04X}) to {:?} {} time(s), first in {:?}",
+            c, *c as u32, table[c], count, example
+        );
+    }
+}
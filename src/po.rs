@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+
+use crate::text::{self, TextEntry};
+use crate::util;
+
+//book and item text can be exported to gettext .po/.pot files for tools like poedit and weblate.
+//every entry's msgctxt is its key from text.rs, e.g. "t_book03/2/1/0", and its msgid the text as it
+//is in the table. on import the msgstr of every entry that is translated and not fuzzy replaces the
+//text with that key before the table is encoded again. empty text isn't exported
+const HEADER: &str = "msgid \"\"\n\
+                      msgstr \"\"\n\
+                      \"MIME-Version: 1.0\\n\"\n\
+                      \"Content-Type: text/plain; charset=UTF-8\\n\"\n\
+                      \"Content-Transfer-Encoding: 8bit\\n\"\n";
+
+pub fn export_po(path: String, template: bool) -> io::Result<()> {
+    let entries = text::entries(&text::file_name(&path)?, &fs::read(&path)?)?;
+    let extension = if template { "pot" } else { "po" };
+    util::write_output(&path, extension, entries_to_po(&entries).as_bytes())
+}
+
+pub fn import_po(path: String, po_path: String, output_path: String) -> io::Result<()> {
+    let translations = parse_po(&fs::read_to_string(&po_path)?)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", po_path, e)))?;
+    let data = text::apply(&text::file_name(&path)?, &fs::read(&path)?, &translations)?;
+
+    println!(
+        "Applied {} translations from {}",
+        translations.len(),
+        po_path
+    );
+    fs::write(output_path, data)
+}
+
+//NOTE: code for writing po files------------------------------------------------------------------
+fn entries_to_po(entries: &[TextEntry]) -> String {
+    let mut po = HEADER.to_string();
+    for entry in entries.iter().filter(|entry| !entry.text.is_empty()) {
+        po.push('\n');
+        po.push_str(&format!("msgctxt {}\n", quote(&entry.key)));
+        po.push_str(&format!("msgid {}\n", quote(&entry.text)));
+        po.push_str("msgstr \"\"\n");
+    }
+
+    po
+}
+
+//quote a string for a po file. text with line breaks is split after each of them, as gettext does
+fn quote(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\t', "\\t")
+        .replace('\r', "\\r")
+        .replace('\n', "\\n");
+
+    if !text.contains('\n') {
+        return format!("\"{}\"", escaped);
+    }
+    let mut quoted = "\"\"".to_string();
+    for line in escaped.split_inclusive("\\n") {
+        quoted.push_str(&format!("\n\"{}\"", line));
+    }
+    quoted
+}
+
+//NOTE: code for reading po files------------------------------------------------------------------
+#[derive(Default)]
+struct PoEntry {
+    context: Option<String>,
+    id: Option<String>,
+    translation: Option<String>,
+    fuzzy: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Field {
+    Context,
+    Id,
+    Translation,
+    //msgid_plural and the other plural forms, which gospel has no use for
+    Ignored,
+}
+
+//read the translations of a po file, keyed by msgctxt
+fn parse_po(po: &str) -> io::Result<HashMap<String, String>> {
+    let mut translations = HashMap::new();
+    let mut entry = PoEntry::default();
+    let mut field = Field::Ignored;
+
+    for (i, line) in po.lines().enumerate() {
+        let line = line.trim();
+        let invalid = |message: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: {}", i + 1, message),
+            )
+        };
+
+        //a new entry starts at its first comment or keyword after the previous one's msgstr, so
+        //that its flags aren't given to the previous entry
+        let starts_entry =
+            line.starts_with('#') || line.starts_with("msgctxt ") || line.starts_with("msgid ");
+        if line.is_empty() || (starts_entry && entry.translation.is_some()) {
+            finish_entry(&mut translations, std::mem::take(&mut entry)).map_err(|e| invalid(&e))?;
+        }
+
+        let (keyword, value) = match line.split_once(' ') {
+            Some((keyword, value)) if !line.starts_with('"') => (keyword, value),
+            _ => ("", line),
+        };
+        if line.is_empty() || line.starts_with("#~") {
+            continue;
+        } else if let Some(flags) = line.strip_prefix("#,") {
+            entry.fuzzy |= flags.split(',').any(|flag| flag.trim() == "fuzzy");
+            continue;
+        } else if line.starts_with('#') {
+            continue;
+        }
+
+        let value = unquote(value).map_err(|e| invalid(&e))?;
+        field = match keyword {
+            "msgctxt" => Field::Context,
+            "msgid" => Field::Id,
+            "msgstr" | "msgstr[0]" => Field::Translation,
+            "msgid_plural" => Field::Ignored,
+            k if k.starts_with("msgstr[") => Field::Ignored,
+            //a string on its own continues the previous keyword's
+            "" => field,
+            k => return Err(invalid(&format!("unknown keyword {}", k))),
+        };
+        let target = match field {
+            Field::Context => &mut entry.context,
+            Field::Id => &mut entry.id,
+            Field::Translation => &mut entry.translation,
+            Field::Ignored => continue,
+        };
+        target.get_or_insert_with(String::new).push_str(&value);
+    }
+    finish_entry(&mut translations, entry)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("end of file: {}", e)))?;
+
+    Ok(translations)
+}
+
+//keep the translation of a finished entry if it has one that should be used
+fn finish_entry(translations: &mut HashMap<String, String>, entry: PoEntry) -> Result<(), String> {
+    let (Some(context), Some(translation)) = (entry.context, entry.translation) else {
+        //the header and entries gospel didn't write have no msgctxt
+        return Ok(());
+    };
+    if entry.fuzzy || translation.is_empty() {
+        return Ok(());
+    }
+    if translations.contains_key(&context) {
+        return Err(format!(
+            "msgctxt {} is used by more than one entry",
+            context
+        ));
+    }
+
+    translations.insert(context, translation);
+    Ok(())
+}
+
+fn unquote(value: &str) -> Result<String, String> {
+    let inner = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .filter(|_| value.len() >= 2)
+        .ok_or_else(|| format!("expected a quoted string, found {}", value))?;
+
+    let mut unquoted = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unquoted.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unquoted.push('\n'),
+            Some('t') => unquoted.push('\t'),
+            Some('r') => unquoted.push('\r'),
+            Some('"') => unquoted.push('"'),
+            Some('\\') => unquoted.push('\\'),
+            other => {
+                return Err(format!(
+                    "unknown escape \\{} in {}",
+                    other.map_or(String::new(), String::from),
+                    value
+                ));
+            }
+        }
+    }
+
+    Ok(unquoted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoted_text_reads_back_the_same() {
+        let text = "a \"quote\", a \\ and a\ttab\r\nover two lines\n";
+        let po = format!("msgctxt \"k\"\nmsgid \"x\"\nmsgstr {}\n", quote(text));
+        assert_eq!(parse_po(&po).unwrap()["k"], text);
+    }
+
+    #[test]
+    fn multi_line_strings_are_joined() {
+        let po = format!(
+            "{}\n\
+             msgctxt \"t_book03/2/1/0\"\n\
+             msgid \"\"\n\
+             \"first\\n\"\n\
+             \"second\"\n\
+             msgstr \"\"\n\
+             \"erste\\n\"\n\
+             \"zweite\"\n\
+             \n\
+             msgctxt \"t_book03/2/title\"\n\
+             msgid \"Title\"\n\
+             msgstr \"Titel\"\n",
+            HEADER
+        );
+
+        let translations = parse_po(&po).unwrap();
+        assert_eq!(translations.len(), 2);
+        assert_eq!(translations["t_book03/2/1/0"], "erste\nzweite");
+        assert_eq!(translations["t_book03/2/title"], "Titel");
+    }
+
+    #[test]
+    fn fuzzy_untranslated_and_obsolete_entries_are_skipped() {
+        let po = "#, fuzzy\n\
+                  msgctxt \"a\"\n\
+                  msgid \"A\"\n\
+                  msgstr \"guess\"\n\
+                  \n\
+                  msgctxt \"b\"\n\
+                  msgid \"B\"\n\
+                  msgstr \"\"\n\
+                  \n\
+                  #~ msgctxt \"c\"\n\
+                  #~ msgid \"C\"\n\
+                  #~ msgstr \"old\"\n";
+        assert!(parse_po(po).unwrap().is_empty());
+    }
+
+    #[test]
+    fn flags_belong_to_the_entry_after_them() {
+        let po = "msgctxt \"a\"\n\
+                  msgid \"A\"\n\
+                  msgstr \"eins\"\n\
+                  #, fuzzy\n\
+                  #: t_book00\n\
+                  msgctxt \"b\"\n\
+                  msgid \"B\"\n\
+                  msgstr \"zwei\"\n";
+
+        let translations = parse_po(po).unwrap();
+        assert_eq!(translations.len(), 1);
+        assert_eq!(translations["a"], "eins");
+    }
+
+    #[test]
+    fn exported_templates_have_no_translations() {
+        let entries = [
+            TextEntry {
+                key: "t_item2/1/name".to_string(),
+                text: "Tear Balm".to_string(),
+            },
+            TextEntry {
+                key: "t_item2/1/desc".to_string(),
+                text: "Restores\n50 HP.".to_string(),
+            },
+        ];
+        assert!(parse_po(&entries_to_po(&entries)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let error = parse_po("msgctxt \"a\"\nmsgid \"A\"\nmsgstr \"\\q\"\n").unwrap_err();
+        assert_eq!(error.to_string(), "line 3: unknown escape \\q in \"\\q\"");
+
+        let duplicate = "msgctxt \"a\"\nmsgid \"A\"\nmsgstr \"1\"\n\
+                         msgctxt \"a\"\nmsgid \"A\"\nmsgstr \"2\"\n";
+        assert_eq!(
+            parse_po(duplicate).unwrap_err().to_string(),
+            "end of file: msgctxt a is used by more than one entry"
+        );
+    }
+}
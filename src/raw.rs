@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Cursor};
+
+use crate::encoding;
+use crate::util;
+
+//raw mode handles any table that starts with a list of u16 pointers followed by the records they
+//point to, without knowing what the records contain. every distinct pointer target starts a record
+//which runs until the next one (or the end of the file), and null terminated strings found in
+//a record are listed next to its bytes so they can be edited. an edited string is spliced into the
+//record's bytes, so changing its length moves everything after it: only do that in tables whose
+//records don't point into each other
+#[derive(Serialize, Deserialize)]
+pub struct RawTable {
+    //the index of the record each pointer of the pointer list refers to
+    pub pointers: Vec<usize>,
+    pub records: Vec<RawRecord>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RawRecord {
+    //where the record was found, for reference only
+    pub offset: u16,
+    pub bytes: String,
+    pub strings: Vec<RawString>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RawString {
+    //offset of the string from the start of the record and its length in bytes, without the null
+    pub offset: usize,
+    pub length: usize,
+    pub text: String,
+}
+
+pub fn convert_raw_to_json_file(path: String) -> io::Result<()> {
+    let json = bytes_to_json(&fs::read(&path)?)?;
+    util::write_output(&path, "json", json.as_bytes())
+}
+
+pub fn convert_json_to_raw(path: String) -> io::Result<()> {
+    let file_name = util::table_file_name(&path);
+    let dt_data = json_to_bytes(&file_name, &fs::read_to_string(&path)?)?;
+    util::write_output(&path, "_dt", &dt_data)
+}
+
+//dump the contents of any pointer indexed table to json
+pub fn bytes_to_json(data: &[u8]) -> io::Result<String> {
+    util::verified_json("raw table", data, parse_from_bytes, |table| {
+        table_to_byte_data("raw table", table)
+    })
+}
+
+//encode a raw json dump, applying any edited strings and naming the table in errors as file_name
+pub fn json_to_bytes(file_name: &str, json: &str) -> io::Result<Vec<u8>> {
+    util::encode_json(json, |table: &RawTable| {
+        table_to_byte_data(file_name, table)
+    })
+}
+
+//NOTE: code for converting from _dt to json-------------------------------------------------------
+pub fn parse_from_bytes(data: &[u8]) -> io::Result<RawTable> {
+    let (addresses, starts) = find_records(data)?;
+
+    let mut records = Vec::new();
+    for (i, &start) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).map_or(data.len(), |&a| a as usize);
+        let bytes = &data[start as usize..end];
+        records.push(RawRecord {
+            offset: start,
+            bytes: util::to_hex(bytes),
+            strings: find_strings(bytes)?,
+        });
+    }
+
+    //every pointer is the start of exactly one record
+    let pointers = addresses
+        .iter()
+        .map(|address| starts.binary_search(address).unwrap())
+        .collect();
+
+    Ok(RawTable { pointers, records })
+}
+
+//read the pointer list of a table, returning the pointers along with the sorted, distinct addresses
+//at which records start
+pub fn find_records(data: &[u8]) -> io::Result<(Vec<u16>, Vec<u16>)> {
+    let addresses = util::read_pointer_list(&mut Cursor::new(data))?;
+    let header_length = addresses[0] as usize;
+
+    //anything pointing into the pointer list or past the end of the file means this isn't a
+    //pointer indexed table
+    if let Some(address) = addresses
+        .iter()
+        .find(|&&a| (a as usize) < header_length || a as usize > data.len())
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Pointer {:#06X} is outside of the records, not a pointer indexed table",
+                address
+            ),
+        ));
+    }
+
+    let mut starts = addresses.clone();
+    starts.sort_unstable();
+    starts.dedup();
+
+    Ok((addresses, starts))
+}
+
+//find every run of at least two printable characters that ends in a null byte
+pub fn find_strings(bytes: &[u8]) -> io::Result<Vec<RawString>> {
+    let mut strings = Vec::new();
+    let mut start = 0;
+    let mut characters = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let width = printable_width(bytes, i);
+
+        if width == 0 {
+            if bytes[i] == 0x00 && characters >= 2 {
+                strings.push(RawString {
+                    offset: start,
+                    length: i - start,
+                    text: util::decode_string(&bytes[start..i])?,
+                });
+            }
+            characters = 0;
+            i += 1;
+            start = i;
+        } else {
+            characters += 1;
+            i += width;
+        }
+    }
+
+    Ok(strings)
+}
+
+//the length in bytes of the printable character starting at i in the selected encoding, or 0 if
+//there isn't one
+pub fn printable_width(bytes: &[u8], i: usize) -> usize {
+    encoding::current().printable_width(bytes, i)
+}
+
+//NOTE: code for converting from json to _dt-------------------------------------------------------
+fn table_to_byte_data(file_name: &str, table: &RawTable) -> io::Result<Vec<u8>> {
+    let header_length = 2 * table.pointers.len();
+    let mut bytes = vec![0u8; header_length];
+    let mut record_addresses = Vec::new();
+
+    for (i, record) in table.records.iter().enumerate() {
+        record_addresses.push(bytes.len() as u16);
+        bytes.extend(
+            record_bytes(record).map_err(util::in_entry(file_name, format!("record {}", i)))?,
+        );
+    }
+
+    for (i, &record) in table.pointers.iter().enumerate() {
+        let address = record_addresses.get(record).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Pointer {} refers to missing record {}", i, record),
+            )
+        })?;
+        bytes[i * 2..i * 2 + 2].copy_from_slice(&address.to_le_bytes());
+    }
+
+    Ok(bytes)
+}
+
+//the bytes of a record with any edited strings spliced in
+fn record_bytes(record: &RawRecord) -> io::Result<Vec<u8>> {
+    let mut bytes = util::from_hex(&record.bytes)?;
+
+    //splice from the back so that the offsets of earlier strings stay valid
+    let mut strings: Vec<&RawString> = record.strings.iter().collect();
+    strings.sort_by_key(|s| std::cmp::Reverse(s.offset));
+
+    for string in strings {
+        let end = string.offset + string.length;
+        if end > bytes.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "String at {} runs past the end of the record",
+                    string.offset
+                ),
+            ));
+        }
+
+        let encoded = util::encode_string(&string.text)
+            .map_err(|e| io::Error::new(e.kind(), format!("String at {}: {}", string.offset, e)))?;
+        if encoded != bytes[string.offset..end] {
+            bytes.splice(string.offset..end, encoded);
+        }
+    }
+
+    Ok(bytes)
+}
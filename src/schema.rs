@@ -0,0 +1,480 @@
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::fs;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+
+use crate::util;
+
+//a schema describes the layout of a table's records so that tables without their own module can
+//still be converted to json and back. schemas are json files like
+//
+//  {
+//      "layout": "pointer_list",
+//      "fields": [
+//          { "name": "id", "type": "u16" },
+//          { "name": "name", "type": "string_ptr" },
+//          { "name": "slots", "type": "array", "count": 6, "fields": [
+//              { "name": "cost", "type": "u16" }
+//          ] }
+//      ]
+//  }
+//
+//fields are read in order. "string" is a null terminated string stored in the record itself while
+//"string_ptr" is a u16 pointer to a null terminated string, and those strings are written after all
+//of the records when encoding. arrays either have a fixed "count" or take their length from an
+//earlier integer field of the same record named by "count_field"
+#[derive(Deserialize)]
+pub struct Schema {
+    #[serde(default)]
+    layout: Layout,
+    fields: Vec<Field>,
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum Layout {
+    //a list of u16 pointers to the records at the start of the file, like t_item2
+    #[default]
+    PointerList,
+    //records directly after one another until the end of the file
+    Packed,
+}
+
+#[derive(Deserialize)]
+struct Field {
+    name: String,
+    #[serde(flatten)]
+    field_type: FieldType,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum FieldType {
+    U8,
+    U16,
+    U32,
+    String,
+    StringPtr,
+    Array {
+        #[serde(default)]
+        count: Option<usize>,
+        #[serde(default)]
+        count_field: Option<String>,
+        fields: Vec<Field>,
+    },
+}
+
+pub fn convert_dt_to_json_file(path: String, schema_path: String) -> io::Result<()> {
+    let schema = read_schema(&schema_path)?;
+    let json = util::verified_json(
+        &path,
+        &fs::read(&path)?,
+        |data| decode(&schema, data),
+        |records| encode(&schema, &util::table_file_name(&path), records),
+    )?;
+    util::write_output(&path, "json", json.as_bytes())
+}
+
+pub fn convert_json_to_dt(path: String, schema_path: String) -> io::Result<()> {
+    let schema = read_schema(&schema_path)?;
+    let file_name = util::table_file_name(&path);
+    let dt_data = util::encode_json(&fs::read_to_string(&path)?, |records: &Vec<Value>| {
+        encode(&schema, &file_name, records)
+    })?;
+    util::write_output(&path, "_dt", &dt_data)
+}
+
+pub fn read_schema(path: &str) -> io::Result<Schema> {
+    let invalid = |message: String| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid schema {}: {}", path, message),
+        )
+    };
+
+    let schema: Schema =
+        serde_json::from_str(&fs::read_to_string(path)?).map_err(|e| invalid(e.to_string()))?;
+    check_fields("the schema", &schema.fields).map_err(invalid)?;
+    Ok(schema)
+}
+
+//records and array elements without fields would take up no bytes
+fn check_fields(owner: &str, fields: &[Field]) -> Result<(), String> {
+    if fields.is_empty() {
+        return Err(format!("{} has no fields", owner));
+    }
+    for field in fields {
+        if let FieldType::Array { fields, .. } = &field.field_type {
+            check_fields(&format!("array {}", field.name), fields)?;
+        }
+    }
+    Ok(())
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+//NOTE: code for converting from _dt to json-------------------------------------------------------
+pub fn decode(schema: &Schema, data: &[u8]) -> io::Result<Vec<Value>> {
+    let mut reader = Cursor::new(data);
+    let mut records = Vec::new();
+
+    match schema.layout {
+        Layout::PointerList => {
+            let pointers = util::read_pointer_list(&mut reader)?;
+            let bar = util::progress_bar(pointers.len() as u64);
+            for &address in &pointers {
+                reader.seek(SeekFrom::Start(address as u64))?;
+                records.push(decode_record(&mut reader, &schema.fields, &mut 0)?);
+                bar.inc(1);
+            }
+        }
+        Layout::Packed => {
+            //the records end where the first pointed to string starts (or at the end of the file)
+            let mut records_end = data.len();
+            while (reader.position() as usize) < records_end {
+                let start = reader.position();
+                records.push(decode_record(
+                    &mut reader,
+                    &schema.fields,
+                    &mut records_end,
+                )?);
+                //a record that reads nothing, e.g. only empty arrays, would repeat forever
+                if reader.position() == start {
+                    return Err(invalid_data(format!(
+                        "Record {} at {:#06X} is empty, the schema can't split the table into \
+                         records",
+                        records.len() - 1,
+                        start
+                    )));
+                }
+            }
+        }
+    }
+
+    Ok(records)
+}
+
+//decode one record, lowering strings_start to the address of any pointed to string found before it
+fn decode_record(
+    reader: &mut Cursor<&[u8]>,
+    fields: &[Field],
+    strings_start: &mut usize,
+) -> io::Result<Value> {
+    let mut record = Map::new();
+
+    for field in fields {
+        let value = match &field.field_type {
+            FieldType::U8 => {
+                let mut byte = [0u8; 1];
+                reader.read_exact(&mut byte)?;
+                Value::from(byte[0])
+            }
+            FieldType::U16 => Value::from(util::read_u16(reader)?),
+            FieldType::U32 => Value::from(util::read_u32(reader)?),
+            FieldType::String => Value::from(util::parse_string(reader)?),
+            FieldType::StringPtr => {
+                let address = util::read_u16(reader)?;
+                *strings_start = (*strings_start).min(address as usize);
+                let position = reader.position();
+                reader.seek(SeekFrom::Start(address as u64))?;
+                let string = util::parse_string(reader)?;
+                reader.seek(SeekFrom::Start(position))?;
+                Value::from(string)
+            }
+            FieldType::Array {
+                count,
+                count_field,
+                fields,
+            } => {
+                let count = array_count(&field.name, *count, count_field, &record)?;
+                let mut elements = Vec::with_capacity(count);
+                for _ in 0..count {
+                    elements.push(decode_record(reader, fields, strings_start)?);
+                }
+                Value::from(elements)
+            }
+        };
+        record.insert(field.name.clone(), value);
+    }
+
+    Ok(Value::Object(record))
+}
+
+//find the length of an array, either fixed by the schema or taken from an earlier field
+fn array_count(
+    name: &str,
+    count: Option<usize>,
+    count_field: &Option<String>,
+    record: &Map<String, Value>,
+) -> io::Result<usize> {
+    match (count, count_field) {
+        (Some(count), None) => Ok(count),
+        (None, Some(count_field)) => record
+            .get(count_field)
+            .and_then(Value::as_u64)
+            .map(|count| count as usize)
+            .ok_or_else(|| {
+                invalid_data(format!(
+                    "Count field {} of array {} is not an earlier integer field",
+                    count_field, name
+                ))
+            }),
+        _ => Err(invalid_data(format!(
+            "Array {} needs exactly one of count or count_field",
+            name
+        ))),
+    }
+}
+
+//NOTE: code for converting from json to _dt-------------------------------------------------------
+//a string_ptr whose string can only be written once the size of all records is known
+struct PendingString {
+    pointer_position: usize,
+    bytes: Vec<u8>,
+}
+
+//encode records with a schema, naming the table in errors as file_name
+pub fn encode(schema: &Schema, file_name: &str, records: &[Value]) -> io::Result<Vec<u8>> {
+    let header_length = match schema.layout {
+        Layout::PointerList => 2 * records.len(),
+        Layout::Packed => 0,
+    };
+    let mut bytes = vec![0u8; header_length];
+    let mut pending = Vec::new();
+
+    for (i, record) in records.iter().enumerate() {
+        if let Layout::PointerList = schema.layout {
+            let address = bytes.len() as u16;
+            bytes[i * 2..i * 2 + 2].copy_from_slice(&address.to_le_bytes());
+        }
+
+        encode_record(&mut bytes, &mut pending, &schema.fields, record)
+            .map_err(util::in_entry(file_name, format!("record {}", i)))?;
+    }
+
+    //the pointed to strings follow all of the records
+    for string in pending {
+        let address = bytes.len() as u16;
+        bytes[string.pointer_position..string.pointer_position + 2]
+            .copy_from_slice(&address.to_le_bytes());
+        util::write_bytes_to_buffer(&mut bytes, string.bytes);
+    }
+
+    Ok(bytes)
+}
+
+fn encode_record(
+    bytes: &mut Vec<u8>,
+    pending: &mut Vec<PendingString>,
+    fields: &[Field],
+    record: &Value,
+) -> io::Result<()> {
+    let record = record
+        .as_object()
+        .ok_or_else(|| invalid_data("Expected an object".to_string()))?;
+
+    for field in fields {
+        let value = record
+            .get(&field.name)
+            .ok_or_else(|| invalid_data(format!("Missing field {}", field.name)))?;
+
+        match &field.field_type {
+            FieldType::U8 => bytes.push(integer_field::<u8>(&field.name, value)?),
+            FieldType::U16 => bytes.extend(integer_field::<u16>(&field.name, value)?.to_le_bytes()),
+            FieldType::U32 => bytes.extend(integer_field::<u32>(&field.name, value)?.to_le_bytes()),
+            FieldType::String => {
+                util::write_bytes_to_buffer(
+                    bytes,
+                    util::encode_string(string_field(&field.name, value)?)
+                        .map_err(|e| invalid_data(format!("Field {}: {}", field.name, e)))?,
+                );
+            }
+            FieldType::StringPtr => {
+                pending.push(PendingString {
+                    pointer_position: bytes.len(),
+                    bytes: util::encode_string(string_field(&field.name, value)?)
+                        .map_err(|e| invalid_data(format!("Field {}: {}", field.name, e)))?,
+                });
+                bytes.extend([0, 0]);
+            }
+            FieldType::Array {
+                count,
+                count_field,
+                fields,
+            } => {
+                let elements = value
+                    .as_array()
+                    .ok_or_else(|| invalid_data(format!("Field {} is not an array", field.name)))?;
+                let count = array_count(&field.name, *count, count_field, record)?;
+                if elements.len() != count {
+                    return Err(invalid_data(format!(
+                        "Array {} has {} elements but should have {}",
+                        field.name,
+                        elements.len(),
+                        count
+                    )));
+                }
+                for element in elements {
+                    encode_record(bytes, pending, fields, element)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn integer_field<T: TryFrom<u64>>(name: &str, value: &Value) -> io::Result<T> {
+    value
+        .as_u64()
+        .and_then(|number| T::try_from(number).ok())
+        .ok_or_else(|| invalid_data(format!("Field {} is not an integer in range", name)))
+}
+
+fn string_field<'a>(name: &str, value: &'a Value) -> io::Result<&'a str> {
+    value
+        .as_str()
+        .ok_or_else(|| invalid_data(format!("Field {} is not a string", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema(json: Value) -> Schema {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn strings_are_stored_in_the_record_or_after_all_records() {
+        let schema = schema(json!({
+            "fields": [
+                { "name": "id", "type": "u8" },
+                { "name": "file", "type": "string" },
+                { "name": "name", "type": "string_ptr" }
+            ]
+        }));
+        let records = vec![
+            json!({ "id": 1, "file": "ab", "name": "Estelle" }),
+            json!({ "id": 2, "file": "", "name": "Joshua" }),
+        ];
+
+        let bytes = encode(&schema, "t_test._dt", &records).unwrap();
+        let mut expected = vec![0x04, 0x00, 0x0A, 0x00];
+        expected.extend([0x01, b'a', b'b', 0x00, 0x0E, 0x00]);
+        expected.extend([0x02, 0x00, 0x16, 0x00]);
+        expected.extend(b"Estelle\0Joshua\0");
+        assert_eq!(bytes, expected);
+
+        assert_eq!(decode(&schema, &bytes).unwrap(), records);
+    }
+
+    #[test]
+    fn arrays_take_a_fixed_count_or_an_earlier_field() {
+        let schema = schema(json!({
+            "layout": "packed",
+            "fields": [
+                { "name": "pair", "type": "array", "count": 2, "fields": [
+                    { "name": "value", "type": "u16" }
+                ] },
+                { "name": "count", "type": "u8" },
+                { "name": "slots", "type": "array", "count_field": "count", "fields": [
+                    { "name": "cost", "type": "u32" },
+                    { "name": "label", "type": "string_ptr" }
+                ] }
+            ]
+        }));
+        let records = vec![
+            json!({
+                "pair": [{ "value": 1 }, { "value": 0x0203 }],
+                "count": 1,
+                "slots": [{ "cost": 0x04050607, "label": "x" }]
+            }),
+            json!({
+                "pair": [{ "value": 0 }, { "value": 0 }],
+                "count": 0,
+                "slots": []
+            }),
+        ];
+
+        let bytes = encode(&schema, "t_test._dt", &records).unwrap();
+        let mut expected = vec![
+            0x01, 0x00, 0x03, 0x02, 0x01, 0x07, 0x06, 0x05, 0x04, 0x10, 0x00,
+        ];
+        expected.extend([0x00, 0x00, 0x00, 0x00, 0x00]);
+        expected.extend(b"x\0");
+        assert_eq!(bytes, expected);
+
+        //packed records end where the first pointed to string starts
+        assert_eq!(decode(&schema, &bytes).unwrap(), records);
+    }
+
+    #[test]
+    fn empty_records_are_rejected() {
+        let folder = std::env::temp_dir().join(format!("gospel_schema_{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let path = folder.join("empty.json");
+        for (json, message) in [
+            (r#"{ "fields": [] }"#, "the schema has no fields"),
+            (
+                r#"{ "fields": [{ "name": "a", "type": "array", "count": 1, "fields": [] }] }"#,
+                "array a has no fields",
+            ),
+        ] {
+            fs::write(&path, json).unwrap();
+            let error = read_schema(&path.to_string_lossy()).err().unwrap();
+            assert!(error.to_string().ends_with(message), "{}", error);
+        }
+        fs::remove_dir_all(&folder).unwrap();
+
+        //a record made of nothing but an empty array doesn't get through a packed table
+        let schema = schema(json!({
+            "layout": "packed",
+            "fields": [
+                { "name": "slots", "type": "array", "count": 0, "fields": [
+                    { "name": "value", "type": "u8" }
+                ] }
+            ]
+        }));
+        let error = decode(&schema, &[1, 2]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Record 0 at 0x0000 is empty, the schema can't split the table into records"
+        );
+    }
+
+    #[test]
+    fn arrays_must_match_their_count() {
+        let schema = schema(json!({
+            "fields": [
+                { "name": "count", "type": "u8" },
+                { "name": "slots", "type": "array", "count_field": "count", "fields": [
+                    { "name": "value", "type": "u8" }
+                ] }
+            ]
+        }));
+        let records = vec![json!({ "count": 2, "slots": [{ "value": 1 }] })];
+
+        let error = encode(&schema, "t_test._dt", &records).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "t_test._dt, record 0: Array slots has 1 elements but should have 2"
+        );
+    }
+
+    #[test]
+    fn encoding_errors_name_the_table_and_field() {
+        let schema = schema(json!({ "fields": [{ "name": "name", "type": "string_ptr" }] }));
+        let records = vec![json!({ "name": "ok" }), json!({ "name": "\u{1F600}" })];
+
+        let error = encode(&schema, "t_test._dt", &records).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("t_test._dt, record 1: Field name: ")
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::tables;
+use crate::text::{self, TextEntry};
+use crate::util;
+
+//book and item text can be exported to csv or tsv for proofreading in a spreadsheet. items get one
+//row each with their id, and books one row per line with the book, page and line it is on. a book's
+//title is the row of the book with an empty page and line. every text column comes with a column of
+//the original text next to it, so that import can tell which cells were edited and only update
+//those. rows can be reordered or left out and other columns added, but every row has to have the
+//ids of text that is in the table
+struct Layout {
+    ids: &'static [&'static str],
+    //the original and edited column of every piece of text in a row
+    texts: &'static [(&'static str, &'static str)],
+}
+
+const BOOK_LAYOUT: Layout = Layout {
+    ids: &["book", "page", "line"],
+    texts: &[("original", "text")],
+};
+
+const ITEM_LAYOUT: Layout = Layout {
+    ids: &["id"],
+    texts: &[
+        ("original name", "name"),
+        ("original description", "description"),
+    ],
+};
+
+pub fn export_sheet(path: String, tsv: bool) -> io::Result<()> {
+    let file_name = text::file_name(&path)?;
+    let table = tables::table_name(&file_name);
+    let entries = text::entries(&file_name, &fs::read(&path)?)?;
+
+    let layout = layout(&table);
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(if tsv { b'\t' } else { b',' })
+        .from_writer(Vec::new());
+    let mut header: Vec<&str> = layout.ids.to_vec();
+    for (original, edited) in layout.texts {
+        header.extend([original, edited]);
+    }
+    writer.write_record(&header).map_err(io::Error::other)?;
+
+    for row in rows(&table, &entries) {
+        let mut record = row.ids;
+        for text in row.texts {
+            record.extend([text.clone(), text]);
+        }
+        writer.write_record(&record).map_err(io::Error::other)?;
+    }
+
+    let data = writer
+        .into_inner()
+        .map_err(|e| io::Error::other(e.to_string()))?;
+    util::write_output(&path, if tsv { "tsv" } else { "csv" }, &data)
+}
+
+pub fn import_sheet(path: String, sheet_path: String, output_path: String) -> io::Result<()> {
+    let file_name = text::file_name(&path)?;
+    let data = fs::read(&path)?;
+    let known: HashSet<String> = text::entries(&file_name, &data)?
+        .into_iter()
+        .map(|entry| entry.key)
+        .collect();
+
+    let edits = parse_sheet(&tables::table_name(&file_name), &sheet_path, &known).map_err(|e| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", sheet_path, e))
+    })?;
+    let data = text::apply(&file_name, &data, &edits)?;
+
+    println!("Updated {} cells from {}", edits.len(), sheet_path);
+    fs::write(output_path, data)
+}
+
+fn layout(table: &str) -> &'static Layout {
+    if table.starts_with("t_book") {
+        &BOOK_LAYOUT
+    } else {
+        &ITEM_LAYOUT
+    }
+}
+
+//the key from text.rs of a piece of text in a row, e.g. "t_book03/2/1/0" for the line of the row
+//with the ids 2, 1 and 0, or "t_item2/15/desc" for the second text of the row of item 15
+fn key(table: &str, ids: &[&str], text: usize) -> String {
+    match ids {
+        [book, "", ""] => format!("{}/{}/title", table, book),
+        [book, page, line] => format!("{}/{}/{}/{}", table, book, page, line),
+        _ => format!("{}/{}/{}", table, ids[0], ["name", "desc"][text]),
+    }
+}
+
+//NOTE: code for writing sheets--------------------------------------------------------------------
+struct Row {
+    ids: Vec<String>,
+    texts: Vec<String>,
+}
+
+//group the entries of a table into rows, which for items hold both their name and description
+fn rows(table: &str, entries: &[TextEntry]) -> Vec<Row> {
+    let mut rows: Vec<Row> = Vec::new();
+    for entry in entries {
+        let parts: Vec<&str> = entry.key[table.len() + 1..].split('/').collect();
+        let ids: Vec<String> = match parts.as_slice() {
+            [book, "title"] => vec![book.to_string(), String::new(), String::new()],
+            [item, "name" | "desc"] => vec![item.to_string()],
+            ids => ids.iter().map(|id| id.to_string()).collect(),
+        };
+
+        match rows.last_mut() {
+            Some(row) if row.ids == ids => row.texts.push(entry.text.clone()),
+            _ => rows.push(Row {
+                ids,
+                texts: vec![entry.text.clone()],
+            }),
+        }
+    }
+
+    rows
+}
+
+//NOTE: code for reading sheets--------------------------------------------------------------------
+//read the cells of a sheet whose text differs from the original next to it, keyed like text.rs. the
+//delimiter is picked from the extension, tabs for .tsv and commas for anything else
+fn parse_sheet(
+    table: &str,
+    sheet_path: &str,
+    known: &HashSet<String>,
+) -> Result<HashMap<String, String>, String> {
+    let tsv = Path::new(sheet_path)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("tsv"));
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(if tsv { b'\t' } else { b',' })
+        .flexible(true)
+        .from_path(sheet_path)
+        .map_err(|e| e.to_string())?;
+
+    let header = reader.headers().map_err(|e| e.to_string())?.clone();
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|column| column == name)
+            .ok_or_else(|| format!("there is no \"{}\" column", name))
+    };
+    let layout = layout(table);
+    let ids = layout
+        .ids
+        .iter()
+        .map(|name| column(name))
+        .collect::<Result<Vec<_>, _>>()?;
+    let texts = layout
+        .texts
+        .iter()
+        .map(|(original, edited)| Ok((column(original)?, column(edited)?)))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let mut edits = HashMap::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        let line = record.position().map_or(0, |position| position.line());
+        let cell = |i: usize| record.get(i).unwrap_or("");
+        let row_ids: Vec<&str> = ids.iter().map(|&i| cell(i).trim()).collect();
+        let described = layout
+            .ids
+            .iter()
+            .zip(&row_ids)
+            .filter(|(_, id)| !id.is_empty())
+            .map(|(name, id)| format!("{} {}", name, id))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        for (i, &(original, edited)) in texts.iter().enumerate() {
+            let key = key(table, &row_ids, i);
+            if !known.contains(&key) {
+                return Err(format!("line {}: there is no text for {}", line, described));
+            }
+            if cell(edited) != cell(original)
+                && edits.insert(key, cell(edited).to_string()).is_some()
+            {
+                return Err(format!(
+                    "line {}: {} is in more than one row",
+                    line, described
+                ));
+            }
+        }
+    }
+
+    Ok(edits)
+}
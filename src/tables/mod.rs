@@ -1,2 +1,69 @@
+pub mod t_bgmtbl;
 pub mod t_book;
+pub mod t_cook;
+pub mod t_face;
 pub mod t_item2;
+pub mod t_name;
+pub mod t_quest;
+pub mod t_sltget;
+pub mod t_status;
+pub mod t_town;
+
+use std::io;
+
+use crate::game;
+use crate::raw;
+
+//decode the contents of a table to json, picking the codec from the table's file name. tables
+//gospel doesn't have a codec for are dumped in raw mode
+pub fn table_to_json(file_name: &str, data: &[u8]) -> io::Result<String> {
+    let table = table_name(file_name);
+    game::check_table(&table)?;
+
+    match table.as_str() {
+        t if t.starts_with("t_book") => t_book::bytes_to_json(data),
+        "t_item2" => t_item2::bytes_to_json(data),
+        "t_town" => t_town::bytes_to_json(data),
+        "t_name" => t_name::bytes_to_json(data),
+        "t_status" => t_status::bytes_to_json(data),
+        "t_sltget" => t_sltget::bytes_to_json(data),
+        "t_cook" => t_cook::bytes_to_json(data),
+        "t_quest" => t_quest::bytes_to_json(data),
+        "t_face" => t_face::bytes_to_json(data),
+        "t_bgmtbl" => t_bgmtbl::bytes_to_json(data),
+        _ => {
+            eprintln!("No codec for {}, dumping it in raw mode", file_name);
+            raw::bytes_to_json(data)
+        }
+    }
+}
+
+//encode the json representation of a table, picking the codec from the table's file name in the
+//same way as table_to_json
+pub fn json_to_table(file_name: &str, json: &str) -> io::Result<Vec<u8>> {
+    let table = table_name(file_name);
+    game::check_table(&table)?;
+
+    match table.as_str() {
+        t if t.starts_with("t_book") => t_book::json_to_bytes(&format!("{}._dt", table), json),
+        "t_item2" => t_item2::json_to_bytes(json),
+        "t_town" => t_town::json_to_bytes(json),
+        "t_name" => t_name::json_to_bytes(json),
+        "t_status" => t_status::json_to_bytes(json),
+        "t_sltget" => t_sltget::json_to_bytes(json),
+        "t_cook" => t_cook::json_to_bytes(json),
+        "t_quest" => t_quest::json_to_bytes(json),
+        "t_face" => t_face::json_to_bytes(json),
+        "t_bgmtbl" => t_bgmtbl::json_to_bytes(json),
+        _ => raw::json_to_bytes(&format!("{}._dt", table), json),
+    }
+}
+
+//the lowercase name of a table without its extension, e.g. "t_book03" for "T_BOOK03._DT"
+pub fn table_name(file_name: &str) -> String {
+    file_name
+        .split('.')
+        .next()
+        .unwrap_or(file_name)
+        .to_ascii_lowercase()
+}
@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+
+use crate::util;
+
+//t_bgmtbl._dt starts with a list of u16 pointers, one per track. each pointer leads to a 6 byte
+//record: the bgm id, a pointer to the track's file name, whether the track loops and its volume.
+//the file names are stored after all of the records
+#[derive(Serialize, Deserialize)]
+struct Bgm {
+    //the id scripts use to play the track, not the entry's position
+    id: u16,
+    file_name: String,
+    loops: u8,
+    volume: u8,
+}
+
+const RECORD_LENGTH: usize = 6;
+
+pub fn convert_t_bgmtbl_to_json_file(path: String) -> io::Result<()> {
+    let json = bytes_to_json(&fs::read(&path)?)?;
+    util::write_output(&path, "json", json.as_bytes())
+}
+
+pub fn convert_json_to_t_bgmtbl(path: String) -> io::Result<()> {
+    let dt_data = json_to_bytes(&fs::read_to_string(&path)?)?;
+    util::write_output(&path, "_dt", &dt_data)
+}
+
+//decode the contents of a t_bgmtbl._dt file to json
+pub fn bytes_to_json(data: &[u8]) -> io::Result<String> {
+    util::verified_json("t_bgmtbl._dt", data, parse_from_bytes, |tracks| {
+        tracks_to_byte_data(tracks)
+    })
+}
+
+//encode the json representation of a t_bgmtbl._dt file
+pub fn json_to_bytes(json: &str) -> io::Result<Vec<u8>> {
+    util::encode_json(json, |tracks: &Vec<Bgm>| tracks_to_byte_data(tracks))
+}
+
+//NOTE: code for converting from _dt to json-------------------------------------------------------
+fn parse_from_bytes(data: &[u8]) -> io::Result<Vec<Bgm>> {
+    let mut reader = Cursor::new(data);
+    let pointers = util::read_pointer_list(&mut reader)?;
+
+    let bar = util::progress_bar(pointers.len() as u64);
+    let mut tracks = Vec::new();
+
+    for &address in &pointers {
+        reader.seek(SeekFrom::Start(address as u64))?;
+        let id = util::read_u16(&mut reader)?;
+        let name_address = util::read_u16(&mut reader)?;
+        let mut loops_and_volume = [0u8; 2];
+        reader.read_exact(&mut loops_and_volume)?;
+
+        reader.seek(SeekFrom::Start(name_address as u64))?;
+        let file_name = util::parse_string(&mut reader)?;
+
+        tracks.push(Bgm {
+            id,
+            file_name,
+            loops: loops_and_volume[0],
+            volume: loops_and_volume[1],
+        });
+        bar.inc(1);
+    }
+
+    Ok(tracks)
+}
+
+//NOTE: code for converting from json to _dt-------------------------------------------------------
+fn tracks_to_byte_data(tracks: &[Bgm]) -> io::Result<Vec<u8>> {
+    util::ensure_unique_ids("Bgm", tracks.iter().map(|track| track.id))?;
+
+    let header_length = 2 * tracks.len();
+    let mut bytes = vec![0u8; header_length + RECORD_LENGTH * tracks.len()];
+
+    for (i, track) in tracks.iter().enumerate() {
+        let record_address = header_length + i * RECORD_LENGTH;
+        bytes[i * 2..i * 2 + 2].copy_from_slice(&(record_address as u16).to_le_bytes());
+
+        let name_address = bytes.len() as u16;
+        let track_bytes = util::encode_string(&track.file_name)
+            .map_err(util::in_entry("t_bgmtbl._dt", format!("bgm {}", track.id)))?;
+        util::write_bytes_to_buffer(&mut bytes, track_bytes);
+
+        let record = &mut bytes[record_address..record_address + RECORD_LENGTH];
+        record[0..2].copy_from_slice(&track.id.to_le_bytes());
+        record[2..4].copy_from_slice(&name_address.to_le_bytes());
+        record[4] = track.loops;
+        record[5] = track.volume;
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    #[test]
+    fn tracks_round_trip() {
+        let mut bytes = vec![0x04, 0x00, 0x0A, 0x00];
+        bytes.extend([0x0A, 0x00, 0x10, 0x00, 0x01, 0x64]);
+        bytes.extend([0x2C, 0x01, 0x17, 0x00, 0x00, 0x50]);
+        bytes.extend(b"ed6001\0ed6300\0");
+
+        let json = bytes_to_json(&bytes).unwrap();
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value,
+            json!([
+                { "id": 10, "file_name": "ed6001", "loops": 1, "volume": 100 },
+                { "id": 300, "file_name": "ed6300", "loops": 0, "volume": 80 }
+            ])
+        );
+        assert_eq!(json_to_bytes(&json).unwrap(), bytes);
+    }
+
+    #[test]
+    fn duplicate_ids_are_rejected() {
+        let track = |id| json!({ "id": id, "file_name": "", "loops": 0, "volume": 0 });
+        let json = json!([track(10), track(11), track(10)]).to_string();
+
+        let error = json_to_bytes(&json).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(
+            error
+                .to_string()
+                .contains("Bgm id 10 is used by more than one entry")
+        );
+    }
+}
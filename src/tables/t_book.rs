@@ -1,10 +1,12 @@
-use indicatif::{ProgressBar, ProgressStyle};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::fs::File;
-use std::io::{self, Read, Seek, SeekFrom, Write}; //CP932 compatible
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write}; //CP932 compatible
 use std::process;
 
+use crate::tables::t_face;
+use crate::text::TextEntry;
 use crate::util;
 
 #[derive(Serialize, Deserialize)]
@@ -20,6 +22,9 @@ struct Page {
     image_x: Option<u16>,
     image_y: Option<u16>,
     image_id: Option<u16>,
+    //portrait file name resolved from t_face for readability only, ignored when encoding
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    image_file: Option<String>,
     lines: Vec<Line>,
 }
 
@@ -29,8 +34,15 @@ struct Line {
     text: String,
 }
 
-pub fn convert_t_book_to_json_file(path: String) -> io::Result<()> {
-    let table_data = parse_from_file(&path)?;
+pub fn convert_t_book_to_json_file(path: String, faces_path: Option<String>) -> io::Result<()> {
+    let mut books = parse_from_bytes(&fs::read(&path)?)?;
+
+    if let Some(faces_path) = faces_path {
+        let face_file_names = t_face::read_face_file_names(&faces_path)?;
+        resolve_image_files(&mut books, &face_file_names);
+    }
+
+    let table_data = serde_json::to_string_pretty(&books).map_err(std::io::Error::other)?;
     let file_name = util::get_file_name(&path);
 
     if let Some(s) = file_name {
@@ -46,14 +58,10 @@ pub fn convert_t_book_to_json_file(path: String) -> io::Result<()> {
 }
 
 pub fn convert_json_to_t_book(path: String) -> io::Result<()> {
-    let json_data = fs::read_to_string(&path)?;
     let file_name = util::get_file_name(&path);
-    let books: Vec<Book> = serde_json::from_str(&json_data)
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-
-    let dt_data = books_to_byte_data(books);
 
     if let Some(s) = file_name {
+        let dt_data = json_to_bytes(&format!("{}._dt", s), &fs::read_to_string(&path)?)?;
         let mut output = File::create(format!("{}._dt", s))?;
         output.write_all(&dt_data)?;
         output.flush()?;
@@ -65,6 +73,89 @@ pub fn convert_json_to_t_book(path: String) -> io::Result<()> {
     }
 }
 
+//decode the contents of a t_bookXX._dt file to json, without portrait file names
+pub fn bytes_to_json(data: &[u8]) -> io::Result<String> {
+    let books = parse_from_bytes(data)?;
+    serde_json::to_string_pretty(&books).map_err(std::io::Error::other)
+}
+
+//encode the json representation of a t_bookXX._dt file, naming the table in errors as file_name
+pub fn json_to_bytes(file_name: &str, json: &str) -> io::Result<Vec<u8>> {
+    util::encode_json(json, |books: &Vec<Book>| {
+        books_to_byte_data(file_name, books)
+    })
+}
+
+//list the book titles and lines of a t_bookXX._dt file, keyed as "t_book03/2/title" for the title
+//of book 2 and "t_book03/2/1/0" for line 0 of its page 1
+pub fn text_entries(table: &str, data: &[u8]) -> io::Result<Vec<TextEntry>> {
+    Ok(books_text_entries(table, parse_from_bytes(data)?))
+}
+
+//list the book titles and lines of the json representation of a t_bookXX._dt file, keyed like
+//text_entries
+pub fn json_text_entries(table: &str, json: &str) -> io::Result<Vec<TextEntry>> {
+    let books: Vec<Book> =
+        serde_json::from_str(json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(books_text_entries(table, books))
+}
+
+fn books_text_entries(table: &str, books: Vec<Book>) -> Vec<TextEntry> {
+    let mut entries = Vec::new();
+    for book in books {
+        entries.push(TextEntry {
+            key: format!("{}/{}/title", table, book.id),
+            text: book.name,
+        });
+        for page in book.pages {
+            for line in page.lines {
+                entries.push(TextEntry {
+                    key: format!("{}/{}/{}/{}", table, book.id, page.id, line.id),
+                    text: line.text,
+                });
+            }
+        }
+    }
+
+    entries
+}
+
+//replace the book titles and lines of a t_bookXX._dt file that have a translation, keyed like
+//text_entries
+pub fn apply_text(
+    table: &str,
+    data: &[u8],
+    translations: &HashMap<String, String>,
+) -> io::Result<Vec<u8>> {
+    let mut books = parse_from_bytes(data)?;
+    for book in &mut books {
+        if let Some(text) = translations.get(&format!("{}/{}/title", table, book.id)) {
+            book.name = text.clone();
+        }
+        for page in &mut book.pages {
+            for line in &mut page.lines {
+                let key = format!("{}/{}/{}/{}", table, book.id, page.id, line.id);
+                if let Some(text) = translations.get(&key) {
+                    line.text = text.clone();
+                }
+            }
+        }
+    }
+
+    books_to_byte_data(&format!("{}._dt", table), &books)
+}
+
+//fill in the portrait file name of every page that shows a portrait, leaving ids outside of the
+//face table and face clears unnamed
+fn resolve_image_files(books: &mut [Book], face_file_names: &[String]) {
+    for page in books.iter_mut().flat_map(|book| book.pages.iter_mut()) {
+        page.image_file = page
+            .image_id
+            .filter(|&id| id != 0xFFF)
+            .and_then(|id| face_file_names.get(id as usize).cloned());
+    }
+}
+
 //NOTE: code for converting from _dt to json-------------------------------------------------------
 //enum for the status of the current book
 enum ReadStatus {
@@ -75,7 +166,11 @@ enum ReadStatus {
 
 //read one text line from the file and return it with a ReadStatus enum to denote whether the page
 //or book has ended
-fn read_line(file: &mut File, page: &mut Page, line_id: u8) -> io::Result<(Line, ReadStatus)> {
+fn read_line<R: Read + Seek>(
+    file: &mut R,
+    page: &mut Page,
+    line_id: u8,
+) -> io::Result<(Line, ReadStatus)> {
     let mut line = Line {
         id: line_id,
         text: String::new(),
@@ -117,7 +212,7 @@ fn read_line(file: &mut File, page: &mut Page, line_id: u8) -> io::Result<(Line,
             0x07 => {
                 let mut color_byte = [0u8; 1];
                 file.read_exact(&mut color_byte)?;
-                buffer.extend(util::encode_string(&format!("<C:{}>", color_byte[0])));
+                buffer.extend(format!("<C:{}>", color_byte[0]).as_bytes());
             }
             //formatting change (face position/id or text size change)
             0x23 => {
@@ -130,7 +225,7 @@ fn read_line(file: &mut File, page: &mut Page, line_id: u8) -> io::Result<(Line,
 }
 
 //decode a byte vector into a string or print an error and exit
-fn decode_or_exit(bytes: &Vec<u8>, addr: [u8; 2]) -> String {
+fn decode_or_exit(bytes: &[u8], addr: [u8; 2]) -> String {
     util::decode_string(bytes).unwrap_or_else(|e| {
         eprintln!(
             "Error reading string starting at {:02X} {:02X}; {}",
@@ -141,8 +236,8 @@ fn decode_or_exit(bytes: &Vec<u8>, addr: [u8; 2]) -> String {
 }
 
 //match the formatting type and fill out the relevant page data or add it to the buffer
-fn handle_formatting(
-    file: &mut File,
+fn handle_formatting<R: Read>(
+    file: &mut R,
     page: &mut Page,
     buffer: &mut Vec<u8>,
     address_bytes: [u8; 2],
@@ -168,9 +263,9 @@ fn handle_formatting(
             }
             //S, text size
             0x53 => {
-                buffer.extend(util::encode_string("<S:"));
+                buffer.extend(b"<S:");
                 buffer.extend(&value_bytes);
-                buffer.push(util::encode_string(">")[0]);
+                buffer.push(b'>');
                 break;
             }
             //x position of face/image
@@ -195,7 +290,7 @@ fn handle_formatting(
 //parse a byte array as a string and convert to a u16 value (used for image x and y positions and
 //face ids)
 fn parse_u16_string(bytes: &[u8], address: [u8; 2]) -> io::Result<u16> {
-    let decoded = util::decode_string(&bytes.to_vec()).map_err(|e| {
+    let decoded = util::decode_string(bytes).map_err(|e| {
         io::Error::new(
             io::ErrorKind::InvalidData,
             format!(
@@ -217,12 +312,13 @@ fn parse_u16_string(bytes: &[u8], address: [u8; 2]) -> io::Result<u16> {
 
 //read all of the lines for one page out and add them to a page, return the page and a bool
 //determining whether or not the book is done
-fn read_page(file: &mut File, page_id: u8) -> io::Result<(Page, bool)> {
+fn read_page<R: Read + Seek>(file: &mut R, page_id: u8) -> io::Result<(Page, bool)> {
     let mut page = Page {
         id: page_id,
         image_x: None,
         image_y: None,
         image_id: None,
+        image_file: None,
         lines: Vec::new(),
     };
     let mut line_id = 0;
@@ -244,7 +340,7 @@ fn read_page(file: &mut File, page_id: u8) -> io::Result<(Page, bool)> {
 }
 
 //loop through and read all of the pages of a book, return the resulting book
-fn read_book(file: &mut File, book_id: u16, title: String) -> io::Result<Book> {
+fn read_book<R: Read + Seek>(file: &mut R, book_id: u16, title: String) -> io::Result<Book> {
     let mut book = Book {
         id: book_id,
         name: title,
@@ -264,18 +360,14 @@ fn read_book(file: &mut File, book_id: u16, title: String) -> io::Result<Book> {
     Ok(book)
 }
 
-//parse the given bookXX file into json
-fn parse_from_file(path: &str) -> io::Result<String> {
-    let mut file = File::open(path)?;
+//parse the contents of a bookXX file into books
+fn parse_from_bytes(data: &[u8]) -> io::Result<Vec<Book>> {
+    let mut file = Cursor::new(data);
     let mut addr_bytes = [0u8; 2];
     file.read_exact(&mut addr_bytes)?;
     let addr_first = u16::from_le_bytes(addr_bytes);
 
-    let style = ProgressStyle::default_bar()
-        .template("[{bar:40.cyan/blue}] {prefix} {pos}/{len}")
-        .unwrap()
-        .progress_chars("█🮆🮅🮄▀🮃🮂▔ ");
-    let bar = ProgressBar::new((addr_first as u64 - 2) / 4).with_style(style);
+    let bar = util::progress_bar((addr_first as u64 - 2) / 4);
 
     let mut books = Vec::new();
     let mut index = 0u16;
@@ -304,11 +396,11 @@ fn parse_from_file(path: &str) -> io::Result<String> {
         bar.inc(1);
     }
 
-    serde_json::to_string_pretty(&books).map_err(std::io::Error::other)
+    Ok(books)
 }
 
 //NOTE: code for converting from json to _dt-------------------------------------------------------
-fn books_to_byte_data(books: Vec<Book>) -> Vec<u8> {
+fn books_to_byte_data(file_name: &str, books: &[Book]) -> io::Result<Vec<u8>> {
     let mut bytes = Vec::new();
     let mut book_addresses: Vec<u16> = Vec::new();
 
@@ -319,17 +411,14 @@ fn books_to_byte_data(books: Vec<Book>) -> Vec<u8> {
     bytes.resize(book_header_length, 0);
 
     //set up the progress bar
-    let style = ProgressStyle::default_bar()
-        .template("[{bar:40.cyan/blue}] {prefix} {pos}/{len}")
-        .unwrap()
-        .progress_chars("█🮆🮅🮄▀🮃🮂▔ ");
-    let bar = ProgressBar::new(book_count as u64).with_style(style);
+    let bar = util::progress_bar(book_count as u64);
 
     for book in books {
         //record the starting address for this book's name
         let name_address = bytes.len() as u16;
         //write the book's name at that address
-        let name_bytes = util::encode_string(&book.name);
+        let name_bytes = util::encode_string(&book.name)
+            .map_err(util::in_entry(file_name, format!("book {}", book.id)))?;
         bytes.extend(name_bytes);
         //end of string
         bytes.push(0x00);
@@ -367,47 +456,11 @@ fn books_to_byte_data(books: Vec<Book>) -> Vec<u8> {
                 bytes.push(0x46); // 'F' for face/image
             }
             for (line_idx, line) in page.lines.iter().enumerate() {
-                let mut i = 0;
-                while i < line.text.len() {
-                    let remainder = &line.text[i..];
-                    if let Some(rest) = remainder.strip_prefix("<C:") {
-                        // push color change byte
-                        bytes.push(0x07);
-
-                        // find number after <C:
-                        let num_str: String =
-                            rest.chars().take_while(|c| c.is_ascii_digit()).collect();
-
-                        let number = num_str.parse::<u8>().unwrap_or_else(|e| {
-                            println!("Failed to parse string to u8: From {}; {}", line.text, e);
-                            process::exit(1);
-                        });
-
-                        bytes.push(number);
-
-                        // advance i past the whole <C:n> tag
-                        i += remainder
-                            .chars()
-                            .take_while(|c| *c != '>')
-                            .map(|c| c.len_utf8())
-                            .sum::<usize>()
-                            + 1; // +1 for '>'
-                    } else if remainder.starts_with("<S:") {
-                        // existing size change handling
-                        bytes.push(0x23);
-                        bytes.push(remainder.as_bytes()[3]);
-                        bytes.push(0x53);
-                        i += 5; // same as before
-                    } else {
-                        // push normal character as CP932-encoded byte
-                        let mut iter = remainder.chars();
-                        if let Some(c) = iter.next() {
-                            let b = util::encode_string(&c.to_string());
-                            bytes.extend(b);
-                            i += c.len_utf8();
-                        }
-                    }
-                }
+                let line_bytes = util::encode_tagged_text(&line.text).map_err(util::in_entry(
+                    file_name,
+                    format!("book {}, page {}, line {}", book.id, page.id, line_idx),
+                ))?;
+                bytes.extend(line_bytes);
                 // end of line if not last line of page
                 if line_idx + 1 != page.lines.len() {
                     bytes.push(0x01);
@@ -436,5 +489,5 @@ fn books_to_byte_data(books: Vec<Book>) -> Vec<u8> {
         bytes[start..start + 2].copy_from_slice(&address.to_le_bytes());
     }
 
-    bytes
+    Ok(bytes)
 }
@@ -0,0 +1,276 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Cursor, Seek, SeekFrom};
+
+use crate::tables::t_item2;
+use crate::util;
+
+//t_cook._dt starts with a list of u16 pointers, one per recipe. each pointer leads to a record of
+//eight ingredient slots (a u16 item id and a u16 count each), two u16 result item ids, and then the
+//recipe's name as a null terminated string. unused slots are filled with zeroes, but every slot is
+//kept in the json so that the table encodes back to the same bytes
+#[derive(Serialize, Deserialize)]
+struct Recipe {
+    id: u16,
+    name: String,
+    ingredients: Vec<Ingredient>,
+    results: Vec<ItemRef>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Ingredient {
+    item_id: u16,
+    count: u16,
+    //resolved from t_item2 for readability only, ignored when encoding
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    item_name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ItemRef {
+    item_id: u16,
+    //resolved from t_item2 for readability only, ignored when encoding
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    item_name: Option<String>,
+}
+
+const INGREDIENT_SLOTS: usize = 8;
+const RESULT_SLOTS: usize = 2;
+
+pub fn convert_t_cook_to_json_file(path: String, items_path: Option<String>) -> io::Result<()> {
+    let data = fs::read(&path)?;
+    let mut recipes = parse_and_verify(&data)?;
+
+    if let Some(items_path) = items_path {
+        let item_names = t_item2::read_item_names(&items_path)?;
+        resolve_item_names(&mut recipes, &item_names);
+    }
+
+    let json = serde_json::to_string_pretty(&recipes).map_err(io::Error::other)?;
+    util::write_output(&path, "json", json.as_bytes())
+}
+
+pub fn convert_json_to_t_cook(path: String) -> io::Result<()> {
+    let dt_data = json_to_bytes(&fs::read_to_string(&path)?)?;
+    util::write_output(&path, "_dt", &dt_data)
+}
+
+//decode the contents of a t_cook._dt file to json, without item names
+pub fn bytes_to_json(data: &[u8]) -> io::Result<String> {
+    serde_json::to_string_pretty(&parse_and_verify(data)?).map_err(io::Error::other)
+}
+
+//encode the json representation of a t_cook._dt file
+pub fn json_to_bytes(json: &str) -> io::Result<Vec<u8>> {
+    util::encode_json(json, |recipes: &Vec<Recipe>| recipes_to_byte_data(recipes))
+}
+
+fn parse_and_verify(data: &[u8]) -> io::Result<Vec<Recipe>> {
+    util::parse_verified("t_cook._dt", data, parse_from_bytes, |recipes| {
+        recipes_to_byte_data(recipes)
+    })
+}
+
+//fill in the name of every item a recipe refers to, leaving empty slots and ids that aren't in the
+//item table unnamed
+fn resolve_item_names(recipes: &mut [Recipe], item_names: &HashMap<u16, String>) {
+    for recipe in recipes {
+        for ingredient in &mut recipe.ingredients {
+            if ingredient.count != 0 {
+                ingredient.item_name = item_names.get(&ingredient.item_id).cloned();
+            }
+        }
+        for result in &mut recipe.results {
+            if result.item_id != 0 {
+                result.item_name = item_names.get(&result.item_id).cloned();
+            }
+        }
+    }
+}
+
+//NOTE: code for converting from _dt to json-------------------------------------------------------
+fn parse_from_bytes(data: &[u8]) -> io::Result<Vec<Recipe>> {
+    let mut reader = Cursor::new(data);
+    let pointers = util::read_pointer_list(&mut reader)?;
+
+    let bar = util::progress_bar(pointers.len() as u64);
+    let mut recipes = Vec::new();
+
+    for (id, &address) in pointers.iter().enumerate() {
+        reader.seek(SeekFrom::Start(address as u64))?;
+
+        let mut ingredients = Vec::new();
+        for _ in 0..INGREDIENT_SLOTS {
+            let item_id = util::read_u16(&mut reader)?;
+            let count = util::read_u16(&mut reader)?;
+            ingredients.push(Ingredient {
+                item_id,
+                count,
+                item_name: None,
+            });
+        }
+
+        let mut results = Vec::new();
+        for _ in 0..RESULT_SLOTS {
+            let item_id = util::read_u16(&mut reader)?;
+            results.push(ItemRef {
+                item_id,
+                item_name: None,
+            });
+        }
+
+        let name = util::parse_string(&mut reader)?;
+
+        recipes.push(Recipe {
+            id: id as u16,
+            name,
+            ingredients,
+            results,
+        });
+        bar.inc(1);
+    }
+
+    Ok(recipes)
+}
+
+//NOTE: code for converting from json to _dt-------------------------------------------------------
+fn recipes_to_byte_data(recipes: &[Recipe]) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![0u8; 2 * recipes.len()];
+
+    for (i, recipe) in recipes.iter().enumerate() {
+        if recipe.ingredients.len() > INGREDIENT_SLOTS || recipe.results.len() > RESULT_SLOTS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Recipe {} has more than {} ingredients or {} results",
+                    recipe.id, INGREDIENT_SLOTS, RESULT_SLOTS
+                ),
+            ));
+        }
+
+        let address = bytes.len() as u16;
+        bytes[i * 2..i * 2 + 2].copy_from_slice(&address.to_le_bytes());
+
+        //pad any slots missing from the json with zeroes
+        for slot in 0..INGREDIENT_SLOTS {
+            let (item_id, count) = recipe
+                .ingredients
+                .get(slot)
+                .map_or((0, 0), |ingredient| (ingredient.item_id, ingredient.count));
+            bytes.extend(item_id.to_le_bytes());
+            bytes.extend(count.to_le_bytes());
+        }
+        for slot in 0..RESULT_SLOTS {
+            let item_id = recipe.results.get(slot).map_or(0, |result| result.item_id);
+            bytes.extend(item_id.to_le_bytes());
+        }
+
+        let recipe_bytes = util::encode_string(&recipe.name).map_err(util::in_entry(
+            "t_cook._dt",
+            format!("recipe {}", recipe.id),
+        ))?;
+        util::write_bytes_to_buffer(&mut bytes, recipe_bytes);
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    fn recipe_bytes(ingredients: &[(u16, u16)], results: [u16; 2], name: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for slot in 0..INGREDIENT_SLOTS {
+            let (item_id, count) = ingredients.get(slot).copied().unwrap_or((0, 0));
+            bytes.extend(item_id.to_le_bytes());
+            bytes.extend(count.to_le_bytes());
+        }
+        for item_id in results {
+            bytes.extend(item_id.to_le_bytes());
+        }
+        bytes.extend(name);
+        bytes
+    }
+
+    #[test]
+    fn recipes_round_trip() {
+        let mut bytes = vec![0x04, 0x00, 0x2F, 0x00];
+        bytes.extend(recipe_bytes(
+            &[(0x10, 2), (0x11, 1)],
+            [0x0200, 0],
+            b"Omelet\0",
+        ));
+        bytes.extend(recipe_bytes(&[], [0x0201, 0x0202], b"Tea\0"));
+
+        let json = bytes_to_json(&bytes).unwrap();
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value[0]["name"], "Omelet");
+        assert_eq!(value[0]["ingredients"].as_array().unwrap().len(), 8);
+        assert_eq!(
+            value[0]["ingredients"][1],
+            json!({ "item_id": 0x11, "count": 1 })
+        );
+        assert_eq!(
+            value[0]["ingredients"][2],
+            json!({ "item_id": 0, "count": 0 })
+        );
+        assert_eq!(value[1]["id"], 1);
+        assert_eq!(
+            value[1]["results"],
+            json!([{ "item_id": 0x0201 }, { "item_id": 0x0202 }])
+        );
+        assert_eq!(json_to_bytes(&json).unwrap(), bytes);
+
+        //missing slots are padded, and item names are ignored
+        let short = json!([
+            {
+                "id": 0, "name": "Omelet",
+                "ingredients": [
+                    { "item_id": 0x10, "count": 2, "item_name": "Egg" },
+                    { "item_id": 0x11, "count": 1 }
+                ],
+                "results": [{ "item_id": 0x0200 }]
+            },
+            { "id": 1, "name": "Tea", "ingredients": [], "results": [{ "item_id": 0x0201 }, { "item_id": 0x0202 }] }
+        ]);
+        assert_eq!(json_to_bytes(&short.to_string()).unwrap(), bytes);
+    }
+
+    #[test]
+    fn item_names_are_resolved_for_used_slots() {
+        let mut bytes = vec![0x02, 0x00];
+        bytes.extend(recipe_bytes(&[(0x10, 2)], [0x0200, 0], b"Omelet\0"));
+        let mut recipes = parse_from_bytes(&bytes).unwrap();
+        let item_names = HashMap::from([(0x10, "Egg".to_string()), (0, "Nothing".to_string())]);
+
+        resolve_item_names(&mut recipes, &item_names);
+        assert_eq!(recipes[0].ingredients[0].item_name.as_deref(), Some("Egg"));
+        assert_eq!(recipes[0].ingredients[1].item_name, None);
+        assert_eq!(recipes[0].results[0].item_name, None);
+        assert_eq!(recipes[0].results[1].item_name, None);
+    }
+
+    #[test]
+    fn too_many_slots_are_rejected() {
+        let ingredient = json!({ "item_id": 1, "count": 1 });
+        let result = json!({ "item_id": 1 });
+        for (ingredients, results) in [(9, 1), (1, 3)] {
+            let recipe = json!([{
+                "id": 0, "name": "",
+                "ingredients": vec![ingredient.clone(); ingredients],
+                "results": vec![result.clone(); results]
+            }]);
+
+            let error = json_to_bytes(&recipe.to_string()).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert!(
+                error
+                    .to_string()
+                    .contains("Recipe 0 has more than 8 ingredients or 2 results")
+            );
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Cursor, Seek, SeekFrom};
+
+use crate::util;
+
+//t_face._dt starts with a list of u16 pointers, one per portrait. each pointer leads to the file
+//name of the portrait as a null terminated string. the position of a portrait in the list is the
+//id that book pages use to show it
+#[derive(Serialize, Deserialize)]
+struct Face {
+    id: u16,
+    file_name: String,
+}
+
+pub fn convert_t_face_to_json_file(path: String) -> io::Result<()> {
+    let json = bytes_to_json(&fs::read(&path)?)?;
+    util::write_output(&path, "json", json.as_bytes())
+}
+
+pub fn convert_json_to_t_face(path: String) -> io::Result<()> {
+    let dt_data = json_to_bytes(&fs::read_to_string(&path)?)?;
+    util::write_output(&path, "_dt", &dt_data)
+}
+
+//decode the contents of a t_face._dt file to json
+pub fn bytes_to_json(data: &[u8]) -> io::Result<String> {
+    util::verified_json("t_face._dt", data, parse_from_bytes, |faces| {
+        faces_to_byte_data(faces)
+    })
+}
+
+//encode the json representation of a t_face._dt file
+pub fn json_to_bytes(json: &str) -> io::Result<Vec<u8>> {
+    util::encode_json(json, |faces: &Vec<Face>| faces_to_byte_data(faces))
+}
+
+//read the portrait file names of a t_face._dt file, indexed by image id
+pub fn read_face_file_names(path: &str) -> io::Result<Vec<String>> {
+    let faces = parse_from_bytes(&fs::read(path)?)?;
+    Ok(faces.into_iter().map(|face| face.file_name).collect())
+}
+
+//NOTE: code for converting from _dt to json-------------------------------------------------------
+fn parse_from_bytes(data: &[u8]) -> io::Result<Vec<Face>> {
+    let mut reader = Cursor::new(data);
+    let pointers = util::read_pointer_list(&mut reader)?;
+
+    let bar = util::progress_bar(pointers.len() as u64);
+    let mut faces = Vec::new();
+
+    for (id, &address) in pointers.iter().enumerate() {
+        reader.seek(SeekFrom::Start(address as u64))?;
+        faces.push(Face {
+            id: id as u16,
+            file_name: util::parse_string(&mut reader)?,
+        });
+        bar.inc(1);
+    }
+
+    Ok(faces)
+}
+
+//NOTE: code for converting from json to _dt-------------------------------------------------------
+fn faces_to_byte_data(faces: &[Face]) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![0u8; 2 * faces.len()];
+
+    for (i, face) in faces.iter().enumerate() {
+        let address = bytes.len() as u16;
+        bytes[i * 2..i * 2 + 2].copy_from_slice(&address.to_le_bytes());
+
+        let face_bytes = util::encode_string(&face.file_name)
+            .map_err(util::in_entry("t_face._dt", format!("face {}", face.id)))?;
+        util::write_bytes_to_buffer(&mut bytes, face_bytes);
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    #[test]
+    fn faces_round_trip() {
+        let mut bytes = vec![0x06, 0x00, 0x0E, 0x00, 0x16, 0x00];
+        bytes.extend(b"ka00000\0ka00101\0\0");
+
+        let json = bytes_to_json(&bytes).unwrap();
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value,
+            json!([
+                { "id": 0, "file_name": "ka00000" },
+                { "id": 1, "file_name": "ka00101" },
+                { "id": 2, "file_name": "" }
+            ])
+        );
+        assert_eq!(json_to_bytes(&json).unwrap(), bytes);
+    }
+
+    #[test]
+    fn encoding_errors_name_the_face() {
+        let json =
+            json!([{ "id": 0, "file_name": "ka00000" }, { "id": 1, "file_name": "\u{1F600}" }]);
+
+        let error = json_to_bytes(&json.to_string()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().starts_with("t_face._dt, face 1: "));
+    }
+}
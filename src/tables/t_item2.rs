@@ -1,22 +1,27 @@
-use indicatif::{ProgressBar, ProgressStyle};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::fs::File;
-use std::io::{self, Read, Seek, SeekFrom, Write}; //CP932 compatible
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write}; //CP932 compatible
 use std::process;
 
+use crate::game;
+use crate::text::TextEntry;
 use crate::util;
 
 #[derive(Serialize, Deserialize)]
 struct Item {
-    //FC item table doesn't have item IDs but I add them to the json to make it more readable
+    //FC item table doesn't have item IDs but I add them to the json to make it more readable. SC and
+    //the 3rd store them in front of the text pointers
     item_id: u16,
     item_name: String,
     item_desc: String,
 }
 
 pub fn convert_t_items2_to_json_file(path: String) -> io::Result<()> {
-    let table_data = parse_from_file(&path)?;
+    let items = parse_from_bytes(&fs::read(&path)?)?;
+    //serialize the finalized item list as json data
+    let table_data = serde_json::to_string_pretty(&items).unwrap();
     let file_name = util::get_file_name(&path);
 
     if let Some(s) = file_name {
@@ -32,11 +37,7 @@ pub fn convert_t_items2_to_json_file(path: String) -> io::Result<()> {
 }
 
 pub fn convert_json_to_t_items2(path: String) -> io::Result<()> {
-    let json_data = fs::read_to_string(&path)?;
-    let items: Vec<Item> = serde_json::from_str(&json_data)
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-
-    let dt_data = items_to_byte_data(items);
+    let dt_data = json_to_bytes(&fs::read_to_string(&path)?)?;
 
     let mut file = File::create("t_item2._dt")?;
     file.write_all(&dt_data)?;
@@ -44,8 +45,98 @@ pub fn convert_json_to_t_items2(path: String) -> io::Result<()> {
     Ok(())
 }
 
-fn parse_from_file(path: &str) -> io::Result<String> {
-    let mut file = File::open(path)?;
+//decode the contents of a t_item2._dt file to json
+pub fn bytes_to_json(data: &[u8]) -> io::Result<String> {
+    let items = parse_from_bytes(data)?;
+    serde_json::to_string_pretty(&items).map_err(io::Error::other)
+}
+
+//encode the json representation of a t_item2._dt file
+pub fn json_to_bytes(json: &str) -> io::Result<Vec<u8>> {
+    util::encode_json(json, |items: &Vec<Item>| items_to_byte_data(items))
+}
+
+//read the names of all items in a t_item2._dt file, keyed by item id, so that other tables can show
+//which items they refer to
+pub fn read_item_names(path: &str) -> io::Result<HashMap<u16, String>> {
+    let items = parse_from_bytes(&fs::read(path)?)?;
+    Ok(items
+        .into_iter()
+        .map(|item| (item.item_id, item.item_name))
+        .collect())
+}
+
+//whether an item table stores item ids, telling FC's tables from the later games'. the name pointer
+//of the first item leads right past the record, which is 4 bytes long without an id and 6 with one.
+//None for empty tables and tables that fit neither layout
+pub fn stores_item_ids(data: &[u8]) -> Option<bool> {
+    let read = |position: usize| {
+        data.get(position..position + 2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+    };
+
+    let address = read(0)?;
+    if read(address)? == address + 4 {
+        Some(false)
+    } else if read(address + 2)? == address + 6 {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+//list the item names and descriptions of a t_item2._dt file, keyed as "t_item2/15/name" and
+//"t_item2/15/desc" for item 15
+pub fn text_entries(table: &str, data: &[u8]) -> io::Result<Vec<TextEntry>> {
+    Ok(items_text_entries(table, parse_from_bytes(data)?))
+}
+
+//list the item names and descriptions of the json representation of a t_item2._dt file, keyed like
+//text_entries
+pub fn json_text_entries(table: &str, json: &str) -> io::Result<Vec<TextEntry>> {
+    let items: Vec<Item> =
+        serde_json::from_str(json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(items_text_entries(table, items))
+}
+
+fn items_text_entries(table: &str, items: Vec<Item>) -> Vec<TextEntry> {
+    let mut entries = Vec::new();
+    for item in items {
+        entries.push(TextEntry {
+            key: format!("{}/{}/name", table, item.item_id),
+            text: item.item_name,
+        });
+        entries.push(TextEntry {
+            key: format!("{}/{}/desc", table, item.item_id),
+            text: item.item_desc,
+        });
+    }
+
+    entries
+}
+
+//replace the item names and descriptions of a t_item2._dt file that have a translation, keyed like
+//text_entries
+pub fn apply_text(
+    table: &str,
+    data: &[u8],
+    translations: &HashMap<String, String>,
+) -> io::Result<Vec<u8>> {
+    let mut items = parse_from_bytes(data)?;
+    for item in &mut items {
+        if let Some(text) = translations.get(&format!("{}/{}/name", table, item.item_id)) {
+            item.item_name = text.clone();
+        }
+        if let Some(text) = translations.get(&format!("{}/{}/desc", table, item.item_id)) {
+            item.item_desc = text.clone();
+        }
+    }
+
+    items_to_byte_data(&items)
+}
+
+fn parse_from_bytes(data: &[u8]) -> io::Result<Vec<Item>> {
+    let mut file = Cursor::new(data);
     let mut address_bytes = [0u8; 2];
 
     //get the address of the first datum (the beginning of the file is a collection of addresses
@@ -54,14 +145,12 @@ fn parse_from_file(path: &str) -> io::Result<String> {
     let address_first_datum = u16::from_le_bytes(address_bytes);
 
     //set up the progress bar
-    let style = ProgressStyle::default_bar()
-        .template("[{bar:40.cyan/blue}] {prefix} {pos}/{len}")
-        .unwrap()
-        .progress_chars("█🮆🮅🮄▀🮃🮂▔ ");
-    let bar = ProgressBar::new((address_first_datum as u64 - 2) / 2).with_style(style);
+    let bar = util::progress_bar((address_first_datum as u64 - 2) / 2);
 
     let mut index_current_datum: u16 = 0;
 
+    let has_ids = game::current().has_item_ids();
+
     let mut items = Vec::new();
 
     let mut id = 0;
@@ -74,15 +163,23 @@ fn parse_from_file(path: &str) -> io::Result<String> {
         file.read_exact(&mut address_bytes)?;
         let address_current_datum: u16 = u16::from_le_bytes(address_bytes);
 
-        //move to the address previously read and parse the name and description from the datum
-        file.seek(SeekFrom::Start(address_current_datum as u64 + 4))?;
+        //read the item id if the game stores one, otherwise the item's position is its id
+        file.seek(SeekFrom::Start(address_current_datum as u64))?;
+        let item_id = if has_ids {
+            util::read_u16(&mut file)?
+        } else {
+            id
+        };
+
+        //skip the text pointers and parse the name and description from the datum
+        file.seek(SeekFrom::Current(4))?;
 
         let datum_name = util::parse_string(&mut file)?;
         let datum_desc = util::parse_string(&mut file)?;
 
         //add the item data to the item list
         items.push(Item {
-            item_id: id,
+            item_id,
             item_name: datum_name,
             item_desc: datum_desc,
         });
@@ -93,13 +190,10 @@ fn parse_from_file(path: &str) -> io::Result<String> {
         id += 1;
     }
 
-    //serialize the finalized item list as json data
-    let json = serde_json::to_string_pretty(&items).unwrap();
-
-    Ok(json)
+    Ok(items)
 }
 
-fn items_to_byte_data(items: Vec<Item>) -> Vec<u8> {
+fn items_to_byte_data(items: &[Item]) -> io::Result<Vec<u8>> {
     let mut bytes = Vec::new();
     let mut item_addresses = Vec::new();
 
@@ -109,25 +203,44 @@ fn items_to_byte_data(items: Vec<Item>) -> Vec<u8> {
     //reserve the item address space
     bytes.resize(item_header_length, 0);
 
+    let game = game::current();
+
     //set up the progress bar
-    let style = ProgressStyle::default_bar()
-        .template("[{bar:40.cyan/blue}] {prefix} {pos}/{len}")
-        .unwrap()
-        .progress_chars("█🮆🮅🮄▀🮃🮂▔ ");
-    let bar = ProgressBar::new(item_count as u64).with_style(style);
+    let bar = util::progress_bar(item_count as u64);
 
     for item in items {
         //record the starting address for this item's data
         let address = bytes.len() as u16;
         item_addresses.push(address);
 
+        if game.has_item_ids() {
+            bytes.extend(item.item_id.to_le_bytes());
+        }
+
         //reserve the name and desc address space for this item
         let item_data_address_pos = bytes.len();
         bytes.resize(item_data_address_pos + 4, 0);
 
-        //encode the name and description in CP932
-        let name_bytes = util::encode_string(&item.item_name);
-        let desc_bytes = util::encode_string(&item.item_desc);
+        //encode the name and description in the selected encoding
+        let name_bytes = util::encode_string(&item.item_name).map_err(util::in_entry(
+            "t_item2._dt",
+            format!("item {} name", item.item_id),
+        ))?;
+        let desc_bytes = util::encode_string(&item.item_desc).map_err(util::in_entry(
+            "t_item2._dt",
+            format!("item {} description", item.item_id),
+        ))?;
+
+        //longer names might run out of their box in the menus
+        if name_bytes.len() > game.item_name_limit() {
+            eprintln!(
+                "Warning: the name of item {} is {} bytes long, {} probably only has room for {}",
+                item.item_id,
+                name_bytes.len(),
+                game,
+                game.item_name_limit()
+            );
+        }
 
         //write the name to the buffer followed by a null byte
         let name_address = util::write_bytes_to_buffer(&mut bytes, name_bytes);
@@ -151,5 +264,5 @@ fn items_to_byte_data(items: Vec<Item>) -> Vec<u8> {
         bytes[start..start + 2].copy_from_slice(&address.to_le_bytes());
     }
 
-    bytes
+    Ok(bytes)
 }
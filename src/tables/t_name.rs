@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Cursor, Seek, SeekFrom};
+
+use crate::util;
+
+//t_name._dt starts with a list of u16 pointers, one per character. each pointer leads to a 16 byte
+//record: the character id, a pointer to the character's name and the file ids of the character's
+//field sprite, sprite palette and battle model. the names are stored after all of the records
+#[derive(Serialize, Deserialize)]
+struct Name {
+    //the id the game (and other tables) use to refer to the character, not the entry's position
+    id: u16,
+    name: String,
+    ch_file: u32,
+    cp_file: u32,
+    ms_file: u32,
+}
+
+const RECORD_LENGTH: usize = 16;
+
+pub fn convert_t_name_to_json_file(path: String) -> io::Result<()> {
+    let json = bytes_to_json(&fs::read(&path)?)?;
+    util::write_output(&path, "json", json.as_bytes())
+}
+
+pub fn convert_json_to_t_name(path: String) -> io::Result<()> {
+    let dt_data = json_to_bytes(&fs::read_to_string(&path)?)?;
+    util::write_output(&path, "_dt", &dt_data)
+}
+
+//decode the contents of a t_name._dt file to json
+pub fn bytes_to_json(data: &[u8]) -> io::Result<String> {
+    util::verified_json("t_name._dt", data, parse_from_bytes, |names| {
+        names_to_byte_data(names)
+    })
+}
+
+//encode the json representation of a t_name._dt file
+pub fn json_to_bytes(json: &str) -> io::Result<Vec<u8>> {
+    util::encode_json(json, |names: &Vec<Name>| names_to_byte_data(names))
+}
+
+//NOTE: code for converting from _dt to json-------------------------------------------------------
+fn parse_from_bytes(data: &[u8]) -> io::Result<Vec<Name>> {
+    let mut reader = Cursor::new(data);
+    let pointers = util::read_pointer_list(&mut reader)?;
+
+    let bar = util::progress_bar(pointers.len() as u64);
+    let mut names = Vec::new();
+
+    for &address in &pointers {
+        reader.seek(SeekFrom::Start(address as u64))?;
+        let id = util::read_u16(&mut reader)?;
+        let name_address = util::read_u16(&mut reader)?;
+        let ch_file = util::read_u32(&mut reader)?;
+        let cp_file = util::read_u32(&mut reader)?;
+        let ms_file = util::read_u32(&mut reader)?;
+
+        reader.seek(SeekFrom::Start(name_address as u64))?;
+        let name = util::parse_string(&mut reader)?;
+
+        names.push(Name {
+            id,
+            name,
+            ch_file,
+            cp_file,
+            ms_file,
+        });
+        bar.inc(1);
+    }
+
+    Ok(names)
+}
+
+//NOTE: code for converting from json to _dt-------------------------------------------------------
+fn names_to_byte_data(names: &[Name]) -> io::Result<Vec<u8>> {
+    util::ensure_unique_ids("Character", names.iter().map(|name| name.id))?;
+
+    let header_length = 2 * names.len();
+    let mut bytes = vec![0u8; header_length + RECORD_LENGTH * names.len()];
+
+    for (i, name) in names.iter().enumerate() {
+        let record_address = header_length + i * RECORD_LENGTH;
+        bytes[i * 2..i * 2 + 2].copy_from_slice(&(record_address as u16).to_le_bytes());
+
+        let name_address = bytes.len() as u16;
+        let name_bytes = util::encode_string(&name.name)
+            .map_err(util::in_entry("t_name._dt", format!("name {}", name.id)))?;
+        util::write_bytes_to_buffer(&mut bytes, name_bytes);
+
+        let record = &mut bytes[record_address..record_address + RECORD_LENGTH];
+        record[0..2].copy_from_slice(&name.id.to_le_bytes());
+        record[2..4].copy_from_slice(&name_address.to_le_bytes());
+        record[4..8].copy_from_slice(&name.ch_file.to_le_bytes());
+        record[8..12].copy_from_slice(&name.cp_file.to_le_bytes());
+        record[12..16].copy_from_slice(&name.ms_file.to_le_bytes());
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    #[test]
+    fn names_round_trip() {
+        let mut bytes = vec![0x04, 0x00, 0x14, 0x00];
+        bytes.extend([0x01, 0x00, 0x24, 0x00, 0x02, 0x00, 0x01, 0x00]);
+        bytes.extend([0x03, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00]);
+        bytes.extend([0x07, 0x00, 0x2C, 0x00, 0x05, 0x00, 0x00, 0x00]);
+        bytes.extend([0x06, 0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x00]);
+        bytes.extend(b"Estelle\0Joshua\0");
+
+        let json = bytes_to_json(&bytes).unwrap();
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value,
+            json!([
+                { "id": 1, "name": "Estelle", "ch_file": 0x00010002, "cp_file": 3, "ms_file": 4 },
+                { "id": 7, "name": "Joshua", "ch_file": 5, "cp_file": 6, "ms_file": 7 }
+            ])
+        );
+        assert_eq!(json_to_bytes(&json).unwrap(), bytes);
+    }
+
+    #[test]
+    fn duplicate_ids_are_rejected() {
+        let name = |id| json!({ "id": id, "name": "", "ch_file": 0, "cp_file": 0, "ms_file": 0 });
+        let json = json!([name(1), name(2), name(1)]).to_string();
+
+        let error = json_to_bytes(&json).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(
+            error
+                .to_string()
+                .contains("Character id 1 is used by more than one entry")
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+
+use crate::game;
+use crate::util;
+
+//t_quest._dt starts with a list of u16 pointers, one per quest. each pointer leads to a 16 byte
+//record: the quest id, the chapter, a flags byte, the BP reward, the mira reward and pointers to the
+//quest's title, client and description. the texts are stored after all of the records and use the
+//same color and size control codes as book lines
+#[derive(Serialize, Deserialize)]
+struct Quest {
+    //the id the game uses to refer to the quest, not the entry's position
+    id: u16,
+    chapter: u8,
+    flags: u8,
+    bp: u16,
+    mira: u32,
+    title: String,
+    client: String,
+    description: String,
+}
+
+const RECORD_LENGTH: usize = 16;
+
+pub fn convert_t_quest_to_json_file(path: String) -> io::Result<()> {
+    game::check_table("t_quest")?;
+    let json = bytes_to_json(&fs::read(&path)?)?;
+    util::write_output(&path, "json", json.as_bytes())
+}
+
+pub fn convert_json_to_t_quest(path: String) -> io::Result<()> {
+    game::check_table("t_quest")?;
+    let dt_data = json_to_bytes(&fs::read_to_string(&path)?)?;
+    util::write_output(&path, "_dt", &dt_data)
+}
+
+//decode the contents of a t_quest._dt file to json
+pub fn bytes_to_json(data: &[u8]) -> io::Result<String> {
+    util::verified_json("t_quest._dt", data, parse_from_bytes, |quests| {
+        quests_to_byte_data(quests)
+    })
+}
+
+//encode the json representation of a t_quest._dt file
+pub fn json_to_bytes(json: &str) -> io::Result<Vec<u8>> {
+    util::encode_json(json, |quests: &Vec<Quest>| quests_to_byte_data(quests))
+}
+
+//NOTE: code for converting from _dt to json-------------------------------------------------------
+fn parse_from_bytes(data: &[u8]) -> io::Result<Vec<Quest>> {
+    let mut reader = Cursor::new(data);
+    let pointers = util::read_pointer_list(&mut reader)?;
+
+    let bar = util::progress_bar(pointers.len() as u64);
+    let mut quests = Vec::new();
+
+    for &address in &pointers {
+        reader.seek(SeekFrom::Start(address as u64))?;
+        let id = util::read_u16(&mut reader)?;
+        let mut chapter_and_flags = [0u8; 2];
+        reader.read_exact(&mut chapter_and_flags)?;
+        let bp = util::read_u16(&mut reader)?;
+        let mira = util::read_u32(&mut reader)?;
+        let title_address = util::read_u16(&mut reader)?;
+        let client_address = util::read_u16(&mut reader)?;
+        let description_address = util::read_u16(&mut reader)?;
+
+        quests.push(Quest {
+            id,
+            chapter: chapter_and_flags[0],
+            flags: chapter_and_flags[1],
+            bp,
+            mira,
+            title: read_text(&mut reader, title_address)?,
+            client: read_text(&mut reader, client_address)?,
+            description: read_text(&mut reader, description_address)?,
+        });
+        bar.inc(1);
+    }
+
+    Ok(quests)
+}
+
+//read the null terminated text at the given address, converting its control codes into tags
+fn read_text(reader: &mut Cursor<&[u8]>, address: u16) -> io::Result<String> {
+    reader.seek(SeekFrom::Start(address as u64))?;
+    let mut bytes = Vec::new();
+    let mut byte = [0u8; 1];
+    while reader.read(&mut byte)? != 0 && byte[0] != 0x00 {
+        bytes.push(byte[0]);
+        //the color after a color change can be 0, so it must not be read as the end of the text
+        if byte[0] == 0x07 {
+            reader.read_exact(&mut byte)?;
+            bytes.push(byte[0]);
+        }
+    }
+
+    util::decode_tagged_text(&bytes)
+}
+
+//NOTE: code for converting from json to _dt-------------------------------------------------------
+fn quests_to_byte_data(quests: &[Quest]) -> io::Result<Vec<u8>> {
+    util::ensure_unique_ids("Quest", quests.iter().map(|quest| quest.id))?;
+
+    let header_length = 2 * quests.len();
+    let mut bytes = vec![0u8; header_length + RECORD_LENGTH * quests.len()];
+
+    for (i, quest) in quests.iter().enumerate() {
+        let record_address = header_length + i * RECORD_LENGTH;
+        bytes[i * 2..i * 2 + 2].copy_from_slice(&(record_address as u16).to_le_bytes());
+
+        let mut text_addresses = [0u16; 3];
+        for (address, text) in
+            text_addresses
+                .iter_mut()
+                .zip([&quest.title, &quest.client, &quest.description])
+        {
+            *address = bytes.len() as u16;
+            let text_bytes = util::encode_tagged_text(text)
+                .map_err(util::in_entry("t_quest._dt", format!("quest {}", quest.id)))?;
+            util::write_bytes_to_buffer(&mut bytes, text_bytes);
+        }
+
+        let record = &mut bytes[record_address..record_address + RECORD_LENGTH];
+        record[0..2].copy_from_slice(&quest.id.to_le_bytes());
+        record[2] = quest.chapter;
+        record[3] = quest.flags;
+        record[4..6].copy_from_slice(&quest.bp.to_le_bytes());
+        record[6..10].copy_from_slice(&quest.mira.to_le_bytes());
+        record[10..12].copy_from_slice(&text_addresses[0].to_le_bytes());
+        record[12..14].copy_from_slice(&text_addresses[1].to_le_bytes());
+        record[14..16].copy_from_slice(&text_addresses[2].to_le_bytes());
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    #[test]
+    fn quests_round_trip() {
+        let mut bytes = vec![0x04, 0x00, 0x14, 0x00];
+        bytes.extend([0x01, 0x01, 0x01, 0x80, 0x03, 0x00, 0xDC, 0x05]);
+        bytes.extend([0x00, 0x00, 0x24, 0x00, 0x29, 0x00, 0x30, 0x00]);
+        bytes.extend([0x02, 0x01, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        bytes.extend([0x00, 0x00, 0x3E, 0x00, 0x45, 0x00, 0x46, 0x00]);
+        bytes.extend(b"Lost\0Aidios\0Find \x07\x00it\x01now\0");
+        bytes.extend(b"#2SBig\0\0Done\0");
+
+        let json = bytes_to_json(&bytes).unwrap();
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value,
+            json!([
+                {
+                    "id": 0x0101, "chapter": 1, "flags": 0x80, "bp": 3, "mira": 1500,
+                    "title": "Lost", "client": "Aidios", "description": "Find <C:0>it\nnow"
+                },
+                {
+                    "id": 0x0102, "chapter": 2, "flags": 0, "bp": 0, "mira": 0,
+                    "title": "<S:2>Big", "client": "", "description": "Done"
+                }
+            ])
+        );
+        assert_eq!(json_to_bytes(&json).unwrap(), bytes);
+    }
+
+    #[test]
+    fn duplicate_ids_are_rejected() {
+        let quest = |id| {
+            json!({
+                "id": id, "chapter": 0, "flags": 0, "bp": 0, "mira": 0,
+                "title": "", "client": "", "description": ""
+            })
+        };
+        let json = json!([quest(3), quest(3)]).to_string();
+
+        let error = json_to_bytes(&json).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(
+            error
+                .to_string()
+                .contains("Quest id 3 is used by more than one entry")
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Cursor, Seek, SeekFrom};
+
+use crate::util;
+
+//t_sltget._dt starts with a list of u16 pointers, one per character. each pointer leads to the
+//character's orbment slots, an array of 14 byte records holding the amount of each of the seven
+//kinds of sepith needed to unlock the slot. a character's slots run until the next character's
+//slots start (or the end of the file)
+#[derive(Serialize, Deserialize)]
+struct Character {
+    id: u16,
+    slots: Vec<Slot>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Slot {
+    //slot numbers aren't stored in the table but I add them to the json to make it more readable
+    slot: u8,
+    earth: u16,
+    water: u16,
+    fire: u16,
+    wind: u16,
+    time: u16,
+    space: u16,
+    mirage: u16,
+}
+
+const RECORD_LENGTH: usize = 14;
+
+pub fn convert_t_sltget_to_json_file(path: String) -> io::Result<()> {
+    let json = bytes_to_json(&fs::read(&path)?)?;
+    util::write_output(&path, "json", json.as_bytes())
+}
+
+pub fn convert_json_to_t_sltget(path: String) -> io::Result<()> {
+    let dt_data = json_to_bytes(&fs::read_to_string(&path)?)?;
+    util::write_output(&path, "_dt", &dt_data)
+}
+
+//decode the contents of a t_sltget._dt file to json
+pub fn bytes_to_json(data: &[u8]) -> io::Result<String> {
+    util::verified_json("t_sltget._dt", data, parse_from_bytes, |characters| {
+        Ok(characters_to_byte_data(characters))
+    })
+}
+
+//encode the json representation of a t_sltget._dt file
+pub fn json_to_bytes(json: &str) -> io::Result<Vec<u8>> {
+    util::encode_json(json, |characters: &Vec<Character>| {
+        Ok(characters_to_byte_data(characters))
+    })
+}
+
+//NOTE: code for converting from _dt to json-------------------------------------------------------
+fn parse_from_bytes(data: &[u8]) -> io::Result<Vec<Character>> {
+    let mut reader = Cursor::new(data);
+    let pointers = util::read_pointer_list(&mut reader)?;
+
+    let bar = util::progress_bar(pointers.len() as u64);
+    let mut characters = Vec::new();
+
+    for (id, &address) in pointers.iter().enumerate() {
+        //the slot list ends where the next one begins
+        let length = util::pointed_length(
+            &pointers,
+            id,
+            data.len(),
+            &format!("slot list of character {}", id),
+        )?;
+        if length % RECORD_LENGTH != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Slot list of character {} at {:#06X} is not a whole number of slots",
+                    id, address
+                ),
+            ));
+        }
+
+        reader.seek(SeekFrom::Start(address as u64))?;
+        let mut slots = Vec::new();
+        for slot in 0..length / RECORD_LENGTH {
+            let slot = u8::try_from(slot).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Slot list of character {} at {:#06X} has more than 256 slots",
+                        id, address
+                    ),
+                )
+            })?;
+            slots.push(Slot {
+                slot,
+                earth: util::read_u16(&mut reader)?,
+                water: util::read_u16(&mut reader)?,
+                fire: util::read_u16(&mut reader)?,
+                wind: util::read_u16(&mut reader)?,
+                time: util::read_u16(&mut reader)?,
+                space: util::read_u16(&mut reader)?,
+                mirage: util::read_u16(&mut reader)?,
+            });
+        }
+
+        characters.push(Character {
+            id: id as u16,
+            slots,
+        });
+        bar.inc(1);
+    }
+
+    Ok(characters)
+}
+
+//NOTE: code for converting from json to _dt-------------------------------------------------------
+fn characters_to_byte_data(characters: &[Character]) -> Vec<u8> {
+    let mut bytes = vec![0u8; 2 * characters.len()];
+
+    for (i, character) in characters.iter().enumerate() {
+        let address = bytes.len() as u16;
+        bytes[i * 2..i * 2 + 2].copy_from_slice(&address.to_le_bytes());
+
+        //the slot numbers are only for readability, records are written in the order given
+        for slot in &character.slots {
+            for cost in [
+                slot.earth,
+                slot.water,
+                slot.fire,
+                slot.wind,
+                slot.time,
+                slot.space,
+                slot.mirage,
+            ] {
+                bytes.extend(cost.to_le_bytes());
+            }
+        }
+    }
+
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    fn slot_bytes(cost: u16) -> Vec<u8> {
+        (0..7).flat_map(|i| (cost + i).to_le_bytes()).collect()
+    }
+
+    fn slot(slot: u8, cost: u16) -> Value {
+        json!({
+            "slot": slot, "earth": cost, "water": cost + 1, "fire": cost + 2, "wind": cost + 3,
+            "time": cost + 4, "space": cost + 5, "mirage": cost + 6
+        })
+    }
+
+    #[test]
+    fn slot_lists_round_trip() {
+        let mut bytes = vec![0x04, 0x00, 0x20, 0x00];
+        bytes.extend(slot_bytes(0));
+        bytes.extend(slot_bytes(0x0100));
+        bytes.extend(slot_bytes(50));
+
+        let json = bytes_to_json(&bytes).unwrap();
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value,
+            json!([
+                { "id": 0, "slots": [slot(0, 0), slot(1, 0x0100)] },
+                { "id": 1, "slots": [slot(0, 50)] }
+            ])
+        );
+        assert_eq!(json_to_bytes(&json).unwrap(), bytes);
+    }
+
+    #[test]
+    fn pointers_past_the_next_one_are_rejected() {
+        let mut bytes = vec![0x06, 0x00, 0x14, 0x00, 0x06, 0x00];
+        bytes.extend(slot_bytes(0));
+
+        let error = parse_from_bytes(&bytes).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            error.to_string(),
+            "Pointer 1 to the slot list of character 1 is 0x0014, past pointer 2, 0x0006"
+        );
+    }
+
+    #[test]
+    fn slot_numbers_have_to_fit_a_byte() {
+        let mut bytes = vec![0x02, 0x00];
+        for _ in 0..257 {
+            bytes.extend(slot_bytes(0));
+        }
+
+        let error = parse_from_bytes(&bytes).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("has more than 256 slots"));
+    }
+}
@@ -0,0 +1,430 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+
+use crate::util;
+
+//t_status._dt starts with a list of u16 pointers, one per character. each pointer leads to the
+//character's growth curve, an array of 24 byte records with one record per level starting at level
+//1. a character's curve runs until the next character's curve starts (or the end of the file)
+#[derive(Serialize, Deserialize)]
+struct Character {
+    id: u16,
+    levels: Vec<Level>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Level {
+    //levels aren't stored in the table but I add them to the json to make it more readable
+    level: u16,
+    hp: u32,
+    ep: u16,
+    str: u16,
+    def: u16,
+    ats: u16,
+    adf: u16,
+    dex: u16,
+    agl: u16,
+    mov: u16,
+    spd: u16,
+    dex_rate: u8,
+    agl_rate: u8,
+}
+
+const RECORD_LENGTH: usize = 24;
+
+const CSV_HEADER: [&str; 14] = [
+    "character",
+    "level",
+    "hp",
+    "ep",
+    "str",
+    "def",
+    "ats",
+    "adf",
+    "dex",
+    "agl",
+    "mov",
+    "spd",
+    "dex_rate",
+    "agl_rate",
+];
+
+pub fn convert_t_status_to_json_file(path: String) -> io::Result<()> {
+    let json = bytes_to_json(&fs::read(&path)?)?;
+    util::write_output(&path, "json", json.as_bytes())
+}
+
+pub fn convert_json_to_t_status(path: String) -> io::Result<()> {
+    let dt_data = json_to_bytes(&fs::read_to_string(&path)?)?;
+    util::write_output(&path, "_dt", &dt_data)
+}
+
+pub fn convert_t_status_to_csv_file(path: String) -> io::Result<()> {
+    let characters = parse_and_verify(&fs::read(&path)?)?;
+
+    util::write_output(&path, "csv", &characters_to_csv(&characters)?)
+}
+
+pub fn convert_csv_to_t_status(path: String) -> io::Result<()> {
+    let csv_data = fs::read_to_string(&path)?;
+    let characters = parse_csv(&csv_data)?;
+
+    util::write_output(&path, "_dt", &characters_to_byte_data(&characters)?)
+}
+
+//decode the contents of a t_status._dt file to json
+pub fn bytes_to_json(data: &[u8]) -> io::Result<String> {
+    serde_json::to_string_pretty(&parse_and_verify(data)?).map_err(io::Error::other)
+}
+
+//encode the json representation of a t_status._dt file
+pub fn json_to_bytes(json: &str) -> io::Result<Vec<u8>> {
+    util::encode_json(json, |characters: &Vec<Character>| {
+        characters_to_byte_data(characters)
+    })
+}
+
+fn parse_and_verify(data: &[u8]) -> io::Result<Vec<Character>> {
+    util::parse_verified("t_status._dt", data, parse_from_bytes, |characters| {
+        characters_to_byte_data(characters)
+    })
+}
+
+//NOTE: code for converting from _dt to json-------------------------------------------------------
+fn parse_from_bytes(data: &[u8]) -> io::Result<Vec<Character>> {
+    let mut reader = Cursor::new(data);
+    let pointers = util::read_pointer_list(&mut reader)?;
+
+    let bar = util::progress_bar(pointers.len() as u64);
+    let mut characters = Vec::new();
+
+    for (id, &address) in pointers.iter().enumerate() {
+        //the curve ends where the next one begins
+        let length = util::pointed_length(
+            &pointers,
+            id,
+            data.len(),
+            &format!("growth curve of character {}", id),
+        )?;
+        if length % RECORD_LENGTH != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Growth curve of character {} at {:#06X} is not a whole number of levels",
+                    id, address
+                ),
+            ));
+        }
+
+        reader.seek(SeekFrom::Start(address as u64))?;
+        let mut levels = Vec::new();
+        for level in 1..=(length / RECORD_LENGTH) as u16 {
+            levels.push(read_level(&mut reader, level)?);
+        }
+
+        characters.push(Character {
+            id: id as u16,
+            levels,
+        });
+        bar.inc(1);
+    }
+
+    Ok(characters)
+}
+
+fn read_level<R: Read>(reader: &mut R, level: u16) -> io::Result<Level> {
+    let mut rates = [0u8; 2];
+    let hp = util::read_u32(reader)?;
+    let ep = util::read_u16(reader)?;
+    let str = util::read_u16(reader)?;
+    let def = util::read_u16(reader)?;
+    let ats = util::read_u16(reader)?;
+    let adf = util::read_u16(reader)?;
+    let dex = util::read_u16(reader)?;
+    let agl = util::read_u16(reader)?;
+    let mov = util::read_u16(reader)?;
+    let spd = util::read_u16(reader)?;
+    reader.read_exact(&mut rates)?;
+
+    Ok(Level {
+        level,
+        hp,
+        ep,
+        str,
+        def,
+        ats,
+        adf,
+        dex,
+        agl,
+        mov,
+        spd,
+        dex_rate: rates[0],
+        agl_rate: rates[1],
+    })
+}
+
+//NOTE: code for converting from json to _dt-------------------------------------------------------
+//the table has no room for ids or level numbers, so like in the csv characters have to come in
+//order and each character's levels have to count up from 1
+fn characters_to_byte_data(characters: &[Character]) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![0u8; 2 * characters.len()];
+
+    for (i, character) in characters.iter().enumerate() {
+        if character.id as usize != i {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Found character {} where character {} was expected",
+                    character.id, i
+                ),
+            ));
+        }
+        let address = bytes.len() as u16;
+        bytes[i * 2..i * 2 + 2].copy_from_slice(&address.to_le_bytes());
+
+        for (j, level) in character.levels.iter().enumerate() {
+            if level.level as usize != j + 1 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Found level {} of character {} where level {} was expected",
+                        level.level,
+                        character.id,
+                        j + 1
+                    ),
+                ));
+            }
+            bytes.extend(level.hp.to_le_bytes());
+            for stat in [
+                level.ep, level.str, level.def, level.ats, level.adf, level.dex, level.agl,
+                level.mov, level.spd,
+            ] {
+                bytes.extend(stat.to_le_bytes());
+            }
+            bytes.push(level.dex_rate);
+            bytes.push(level.agl_rate);
+        }
+    }
+
+    Ok(bytes)
+}
+
+//NOTE: code for converting to and from csv--------------------------------------------------------
+type CsvRow = (
+    u16,
+    u16,
+    u32,
+    u16,
+    u16,
+    u16,
+    u16,
+    u16,
+    u16,
+    u16,
+    u16,
+    u16,
+    u8,
+    u8,
+);
+
+fn characters_to_csv(characters: &[Character]) -> io::Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(CSV_HEADER).map_err(io::Error::other)?;
+
+    for character in characters {
+        for l in &character.levels {
+            let row: CsvRow = (
+                character.id,
+                l.level,
+                l.hp,
+                l.ep,
+                l.str,
+                l.def,
+                l.ats,
+                l.adf,
+                l.dex,
+                l.agl,
+                l.mov,
+                l.spd,
+                l.dex_rate,
+                l.agl_rate,
+            );
+            writer.serialize(row).map_err(io::Error::other)?;
+        }
+    }
+
+    writer
+        .into_inner()
+        .map_err(|e| io::Error::other(e.to_string()))
+}
+
+//parse a csv in the format written by characters_to_csv. the table has no room for ids or level
+//numbers, so characters have to come in order with all of their rows together, and each
+//character's levels have to count up from 1
+fn parse_csv(csv: &str) -> io::Result<Vec<Character>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(csv.as_bytes());
+    let header = reader
+        .headers()
+        .map_err(|e| invalid_row(1, &e.to_string()))?;
+    if header.iter().ne(CSV_HEADER) {
+        return Err(invalid_row(
+            1,
+            &format!("expected the columns {}", CSV_HEADER.join(",")),
+        ));
+    }
+
+    let mut characters: Vec<Character> = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| invalid_row(0, &e.to_string()))?;
+        let row = record.position().map_or(0, |position| position.line());
+        let (id, level, hp, ep, str, def, ats, adf, dex, agl, mov, spd, dex_rate, agl_rate): CsvRow =
+            record
+                .deserialize(None)
+                .map_err(|e| invalid_row(row, &e.to_string()))?;
+
+        if characters.last().is_none_or(|character| character.id != id) {
+            if id as usize != characters.len() {
+                return Err(invalid_row(
+                    row,
+                    &format!(
+                        "found character {} where character {} was expected",
+                        id,
+                        characters.len()
+                    ),
+                ));
+            }
+            characters.push(Character {
+                id,
+                levels: Vec::new(),
+            });
+        }
+        let character = characters.last_mut().unwrap();
+
+        let expected_level = character.levels.len() + 1;
+        if level as usize != expected_level {
+            return Err(invalid_row(
+                row,
+                &format!(
+                    "found level {} of character {} where level {} was expected",
+                    level, id, expected_level
+                ),
+            ));
+        }
+        character.levels.push(Level {
+            level,
+            hp,
+            ep,
+            str,
+            def,
+            ats,
+            adf,
+            dex,
+            agl,
+            mov,
+            spd,
+            dex_rate,
+            agl_rate,
+        });
+    }
+
+    Ok(characters)
+}
+
+fn invalid_row(line: u64, reason: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Invalid csv line {}: {}", line, reason),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    fn level_bytes(hp: u32, stat: u16, rate: u8) -> Vec<u8> {
+        let mut bytes = hp.to_le_bytes().to_vec();
+        for i in 0..9 {
+            bytes.extend((stat + i).to_le_bytes());
+        }
+        bytes.extend([rate, rate + 1]);
+        bytes
+    }
+
+    fn level(level: u16, hp: u32, stat: u16, rate: u8) -> Value {
+        json!({
+            "level": level, "hp": hp, "ep": stat, "str": stat + 1, "def": stat + 2,
+            "ats": stat + 3, "adf": stat + 4, "dex": stat + 5, "agl": stat + 6,
+            "mov": stat + 7, "spd": stat + 8, "dex_rate": rate, "agl_rate": rate + 1
+        })
+    }
+
+    #[test]
+    fn growth_curves_round_trip() {
+        let mut bytes = vec![0x04, 0x00, 0x34, 0x00];
+        bytes.extend(level_bytes(100, 10, 1));
+        bytes.extend(level_bytes(0x12345, 20, 3));
+        bytes.extend(level_bytes(90, 30, 5));
+
+        let json = bytes_to_json(&bytes).unwrap();
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value,
+            json!([
+                { "id": 0, "levels": [level(1, 100, 10, 1), level(2, 0x12345, 20, 3)] },
+                { "id": 1, "levels": [level(1, 90, 30, 5)] }
+            ])
+        );
+        assert_eq!(json_to_bytes(&json).unwrap(), bytes);
+
+        let csv = characters_to_csv(&parse_from_bytes(&bytes).unwrap()).unwrap();
+        let characters = parse_csv(std::str::from_utf8(&csv).unwrap()).unwrap();
+        assert_eq!(characters_to_byte_data(&characters).unwrap(), bytes);
+    }
+
+    #[test]
+    fn ids_and_levels_have_to_be_in_order() {
+        for (characters, message) in [
+            (
+                json!([{ "id": 0, "levels": [] }, { "id": 0, "levels": [] }]),
+                "Found character 0 where character 1 was expected",
+            ),
+            (
+                json!([{ "id": 1, "levels": [] }]),
+                "Found character 1 where character 0 was expected",
+            ),
+            (
+                json!([{ "id": 0, "levels": [level(2, 0, 0, 0)] }]),
+                "Found level 2 of character 0 where level 1 was expected",
+            ),
+        ] {
+            let error = json_to_bytes(&characters.to_string()).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert!(error.to_string().contains(message), "{}", error);
+        }
+
+        let csv = format!("{}\n1,1,0,0,0,0,0,0,0,0,0,0,0,0\n", CSV_HEADER.join(","));
+        let error = parse_csv(&csv).err().unwrap();
+        assert!(
+            error
+                .to_string()
+                .contains("found character 1 where character 0 was expected")
+        );
+    }
+
+    #[test]
+    fn pointers_past_the_next_one_are_rejected() {
+        let mut bytes = vec![0x06, 0x00, 0x1E, 0x00, 0x06, 0x00];
+        bytes.extend(level_bytes(1, 1, 1));
+
+        let error = parse_from_bytes(&bytes).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            error.to_string(),
+            "Pointer 1 to the growth curve of character 1 is 0x001E, past pointer 2, 0x0006"
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+
+use crate::util;
+
+//t_town._dt starts with a list of u16 pointers, one per town. each pointer leads to the town's
+//name as a null terminated string, followed by a single byte for the town type. towns without a
+//name have no type byte
+#[derive(Serialize, Deserialize)]
+struct Town {
+    id: u16,
+    name: String,
+    town_type: Option<u8>,
+}
+
+pub fn convert_t_town_to_json_file(path: String) -> io::Result<()> {
+    let json = bytes_to_json(&fs::read(&path)?)?;
+    util::write_output(&path, "json", json.as_bytes())
+}
+
+pub fn convert_json_to_t_town(path: String) -> io::Result<()> {
+    let dt_data = json_to_bytes(&fs::read_to_string(&path)?)?;
+    util::write_output(&path, "_dt", &dt_data)
+}
+
+//decode the contents of a t_town._dt file to json
+pub fn bytes_to_json(data: &[u8]) -> io::Result<String> {
+    util::verified_json("t_town._dt", data, parse_from_bytes, |towns| {
+        towns_to_byte_data(towns)
+    })
+}
+
+//encode the json representation of a t_town._dt file
+pub fn json_to_bytes(json: &str) -> io::Result<Vec<u8>> {
+    util::encode_json(json, |towns: &Vec<Town>| towns_to_byte_data(towns))
+}
+
+//NOTE: code for converting from _dt to json-------------------------------------------------------
+fn parse_from_bytes(data: &[u8]) -> io::Result<Vec<Town>> {
+    let mut reader = Cursor::new(data);
+    let pointers = util::read_pointer_list(&mut reader)?;
+
+    let bar = util::progress_bar(pointers.len() as u64);
+    let mut towns = Vec::new();
+
+    for (id, &address) in pointers.iter().enumerate() {
+        reader.seek(SeekFrom::Start(address as u64))?;
+        let name = util::parse_string(&mut reader)?;
+
+        //only named towns carry a type byte
+        let town_type = if name.is_empty() {
+            None
+        } else {
+            let mut type_byte = [0u8; 1];
+            reader.read_exact(&mut type_byte)?;
+            Some(type_byte[0])
+        };
+
+        towns.push(Town {
+            id: id as u16,
+            name,
+            town_type,
+        });
+        bar.inc(1);
+    }
+
+    Ok(towns)
+}
+
+//NOTE: code for converting from json to _dt-------------------------------------------------------
+fn towns_to_byte_data(towns: &[Town]) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut town_addresses = Vec::new();
+
+    //reserve the town address space
+    bytes.resize(2 * towns.len(), 0);
+
+    for town in towns {
+        town_addresses.push(bytes.len() as u16);
+
+        //the type byte is only there after a name, so a type without one couldn't be written
+        if town.name.is_empty() != town.town_type.is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Town {} needs both a name and a town_type, or neither",
+                    town.id
+                ),
+            ));
+        }
+
+        let town_bytes = util::encode_string(&town.name)
+            .map_err(util::in_entry("t_town._dt", format!("town {}", town.id)))?;
+        util::write_bytes_to_buffer(&mut bytes, town_bytes);
+        if let Some(town_type) = town.town_type {
+            bytes.push(town_type);
+        }
+    }
+
+    //fill the address space for the town list
+    for (i, &address) in town_addresses.iter().enumerate() {
+        let start = i * 2;
+        bytes[start..start + 2].copy_from_slice(&address.to_le_bytes());
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    #[test]
+    fn towns_round_trip() {
+        let mut bytes = vec![0x06, 0x00, 0x0E, 0x00, 0x0F, 0x00];
+        bytes.extend(b"Rolent\0\x01");
+        bytes.push(0x00);
+        bytes.extend(b"Bose\0\x02");
+
+        let json = bytes_to_json(&bytes).unwrap();
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value,
+            json!([
+                { "id": 0, "name": "Rolent", "town_type": 1 },
+                { "id": 1, "name": "", "town_type": null },
+                { "id": 2, "name": "Bose", "town_type": 2 }
+            ])
+        );
+        assert_eq!(json_to_bytes(&json).unwrap(), bytes);
+    }
+
+    #[test]
+    fn name_and_type_have_to_agree() {
+        for town in [
+            json!({ "id": 0, "name": "Rolent", "town_type": null }),
+            json!({ "id": 0, "name": "", "town_type": 1 }),
+        ] {
+            let error = json_to_bytes(&json!([town]).to_string()).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert!(error.to_string().contains("Town 0 needs both"));
+        }
+    }
+}
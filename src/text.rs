@@ -0,0 +1,94 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::normalize;
+use crate::tables::{self, t_book, t_item2};
+
+//the text translators work on, shared by the translation file formats. every piece of text is keyed
+//by the table and where it sits in it, so that translations can be matched back to it even after
+//the file has been through other tools
+pub struct TextEntry {
+    pub key: String,
+    pub text: String,
+}
+
+//the translatable text of a table, picking the table from its file name
+pub fn entries(file_name: &str, data: &[u8]) -> io::Result<Vec<TextEntry>> {
+    let table = tables::table_name(file_name);
+    match table.as_str() {
+        t if t.starts_with("t_book") => t_book::text_entries(&table, data),
+        "t_item2" => t_item2::text_entries(&table, data),
+        _ => Err(no_text(file_name)),
+    }
+}
+
+//the translatable text of a table or its json representation, which is read as it is so that it can
+//come from a decoding made with a different encoding
+pub fn read_entries(path: &str) -> io::Result<Vec<TextEntry>> {
+    let file_name = file_name(path)?;
+    let is_json = Path::new(path)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    if !is_json {
+        return entries(&file_name, &fs::read(path)?);
+    }
+
+    let json = fs::read_to_string(path)?;
+    let table = tables::table_name(&file_name);
+    match table.as_str() {
+        t if t.starts_with("t_book") => t_book::json_text_entries(&table, &json),
+        "t_item2" => t_item2::json_text_entries(&table, &json),
+        _ => Err(no_text(&file_name)),
+    }
+}
+
+//replace the text of a table with translations keyed like entries, keeping text without one. keys
+//that aren't in the table are an error, as they mean the translations are for a different table
+pub fn apply(
+    file_name: &str,
+    data: &[u8],
+    translations: &HashMap<String, String>,
+) -> io::Result<Vec<u8>> {
+    let known: HashSet<String> = entries(file_name, data)?
+        .into_iter()
+        .map(|entry| entry.key)
+        .collect();
+    if let Some(key) = translations.keys().find(|key| !known.contains(*key)) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} has no text with the key {}", file_name, key),
+        ));
+    }
+
+    let translations: HashMap<String, String> = translations
+        .iter()
+        .map(|(key, text)| (key.clone(), normalize::normalize(text)))
+        .collect();
+
+    let table = tables::table_name(file_name);
+    match table.as_str() {
+        t if t.starts_with("t_book") => t_book::apply_text(&table, data, &translations),
+        "t_item2" => t_item2::apply_text(&table, data, &translations),
+        _ => Err(no_text(file_name)),
+    }
+}
+
+//the file name of a table's path, used to pick the table
+pub fn file_name(path: &str) -> io::Result<String> {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Not a valid file path."))
+}
+
+fn no_text(file_name: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!(
+            "{} has no translatable text, only book and item tables do",
+            file_name
+        ),
+    )
+}
@@ -1,6 +1,16 @@
-use encoding_rs::SHIFT_JIS;
-use std::io::{self, Read};
-use std::path::Path; //CP932 compatible
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::config;
+use crate::encoding;
+use crate::glyphs;
+use crate::normalize;
 
 //parse a string from the current position in a given buffer, assuming the end of the string is
 //denoted with b'\0'
@@ -27,36 +37,471 @@ pub fn parse_string<R: Read>(reader: &mut R) -> io::Result<String> {
     parse_string_until_byte(reader, b'\0')
 }
 
-pub fn decode_string(buffer: &Vec<u8>) -> io::Result<String> {
-    //decode the string from CP932
-    let (decoded, _, error) = SHIFT_JIS.decode(buffer);
-    if error {
-        eprintln!("Error during decoding string. Bytes: {:?}", buffer);
+//decode a string with the selected encoding, CP932 unless told otherwise, and the glyph map if one
+//is loaded. bytes that aren't valid in the encoding are kept as <X:hex> tags, e.g. <X:81FF>, which
+//encode_string turns back into the same bytes. a '<' that starts a literal "<X:" in the table is
+//escaped as well, so that it isn't read back as a tag
+pub fn decode_string(buffer: &[u8]) -> io::Result<String> {
+    let encoding = encoding::current();
+    let mut decoded = String::new();
+    let mut invalid = Vec::new();
+    let mut i = 0;
+
+    while i < buffer.len() {
+        let remainder = &buffer[i..];
+        match glyphs::decode(remainder).or_else(|| encoding.decode_char(remainder)) {
+            Some(('<', length)) if remainder.starts_with(b"<X:") => {
+                invalid.extend(&remainder[..length]);
+                i += length;
+            }
+            Some((c, length)) => {
+                push_escape(&mut decoded, &mut invalid);
+                decoded.push(c);
+                i += length;
+            }
+            //only the first byte is given up on, the next one may start a valid character
+            None => {
+                invalid.push(buffer[i]);
+                i += 1;
+            }
+        }
     }
+    push_escape(&mut decoded, &mut invalid);
 
-    Ok(decoded.into_owned())
+    Ok(decoded)
+}
+
+//write a run of invalid bytes as a single escape tag
+fn push_escape(decoded: &mut String, invalid: &mut Vec<u8>) {
+    if !invalid.is_empty() {
+        decoded.push_str(&format!("<X:{}>", to_hex(invalid).to_ascii_uppercase()));
+        invalid.clear();
+    }
 }
 
-//encode a string into a byte array using CP932
-pub fn encode_string(s: &str) -> Vec<u8> {
-    //encode a string into a byte array using CP932
-    let (bytes, _, _) = SHIFT_JIS.encode(s);
-    bytes.into_owned()
+//the bytes of an <X:hex> tag at the start of text along with the tag's length, or None if text
+//doesn't start with a well formed one
+fn parse_escape(text: &str) -> Option<(Vec<u8>, usize)> {
+    let rest = text.strip_prefix("<X:")?;
+    let end = rest.find('>')?;
+    let hex = &rest[..end];
+    if hex.is_empty() || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some((from_hex(hex).ok()?, end + 4))
+}
+
+//encode a string into a byte array using the selected encoding. characters the encoding has no
+//bytes for are replaced with their substitution from the project config, and are an error without
+//one
+pub fn encode_string(s: &str) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut i = 0;
+
+    while let Some(c) = s[i..].chars().next() {
+        if let Some((escaped, length)) = parse_escape(&s[i..]) {
+            bytes.extend(escaped);
+            i += length;
+        } else {
+            bytes.extend(encode_char(s, i, c)?);
+            i += c.len_utf8();
+        }
+    }
+
+    Ok(bytes)
+}
+
+//encode the character at byte index i of a string
+fn encode_char(s: &str, i: usize, c: char) -> io::Result<Vec<u8>> {
+    let encoding = encoding::current();
+    let encode = |c| glyphs::encode(c).or_else(|| encoding.encode_char(c));
+    if let Some(bytes) = encode(c) {
+        return Ok(bytes);
+    }
+
+    let position = s[..i].chars().count() + 1;
+    let substitution = config::get().substitutions.get(&c).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "'{}' (U+{:04X}) at character {} of \"{}\" can't be encoded in {}, add a \
+                 substitution for it to {} to replace it",
+                c,
+                c as u32,
+                position,
+                s,
+                encoding,
+                config::CONFIG_FILE
+            ),
+        )
+    })?;
+
+    //the substitution has to be encodable as it is, it isn't substituted again
+    let mut bytes = Vec::new();
+    for replacement in substitution.chars() {
+        bytes.extend(encode(replacement).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "The substitution \"{}\" for '{}' at character {} of \"{}\" can't be \
+                     encoded in {}",
+                    substitution, c, position, s, encoding
+                ),
+            )
+        })?);
+    }
+
+    Ok(bytes)
+}
+
+//prefix an error with the table and entry it happened in, for use with map_err
+pub fn in_entry(table: &str, entry: impl Display) -> impl FnOnce(io::Error) -> io::Error {
+    let context = format!("{}, {}", table, entry);
+    move |e| io::Error::new(e.kind(), format!("{}: {}", context, e))
+}
+
+//decode text that uses the same control codes as book lines, turning color changes into <C:n>
+//tags, text size changes into <S:n> tags and line breaks into newlines
+pub fn decode_tagged_text(bytes: &[u8]) -> io::Result<String> {
+    let mut buffer = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            //line break
+            0x01 => buffer.push(b'\n'),
+            //color change, followed by the color
+            0x07 if i + 1 < bytes.len() => {
+                buffer.extend(format!("<C:{}>", bytes[i + 1]).as_bytes());
+                i += 1;
+            }
+            //text size change, written as #nS. any other formatting is kept as plain text
+            0x23 => {
+                let digits = bytes[i + 1..]
+                    .iter()
+                    .take_while(|b| b.is_ascii_digit())
+                    .count();
+                if digits > 0 && bytes.get(i + 1 + digits) == Some(&0x53) {
+                    buffer.extend(b"<S:");
+                    buffer.extend(&bytes[i + 1..i + 1 + digits]);
+                    buffer.push(b'>');
+                    i += digits + 1;
+                } else {
+                    buffer.push(0x23);
+                }
+            }
+            b => buffer.push(b),
+        }
+        i += 1;
+    }
+
+    decode_string(&buffer)
+}
+
+//encode text containing <C:n> and <S:n> tags back into the control codes used by the game
+pub fn encode_tagged_text(text: &str) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut i = 0;
+
+    while i < text.len() {
+        let remainder = &text[i..];
+        if let Some(rest) = remainder.strip_prefix("<C:") {
+            //color change byte followed by the color
+            let (number, length) = parse_tag_number(rest)?;
+            bytes.push(0x07);
+            bytes.push(u8::try_from(number).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Color {} is out of range: {}", number, e),
+                )
+            })?);
+            i += 3 + length;
+        } else if let Some(rest) = remainder.strip_prefix("<S:") {
+            //size change, the size is written out as digits between # and S
+            let (number, length) = parse_tag_number(rest)?;
+            bytes.push(0x23);
+            bytes.extend(number.to_string().as_bytes());
+            bytes.push(0x53);
+            i += 3 + length;
+        } else if let Some(rest) = remainder.strip_prefix('\n') {
+            bytes.push(0x01);
+            i = text.len() - rest.len();
+        } else if let Some((escaped, length)) = parse_escape(remainder) {
+            //bytes that weren't valid text when decoded
+            bytes.extend(escaped);
+            i += length;
+        } else if let Some(c) = remainder.chars().next() {
+            //push normal character as bytes in the selected encoding
+            bytes.extend(encode_char(text, i, c)?);
+            i += c.len_utf8();
+        }
+    }
+
+    Ok(bytes)
+}
+
+//parse the number at the start of a tag's contents, returning it along with the length of the
+//number and the closing '>'
+fn parse_tag_number(rest: &str) -> io::Result<(u32, usize)> {
+    let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+    if !rest[digits..].starts_with('>') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Unterminated tag before {}", rest),
+        ));
+    }
+
+    let number = rest[..digits].parse::<u32>().map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Failed to parse tag number from {}: {}", rest, e),
+        )
+    })?;
+
+    Ok((number, digits + 1))
 }
 
 //write an array of bytes to a buffer, followed by a null byte, and return the address of the first
 //byte written
 pub fn write_bytes_to_buffer(buffer: &mut Vec<u8>, bytes: Vec<u8>) -> u16 {
-    let address = bytes.len() as u16;
+    let address = buffer.len() as u16;
     buffer.extend_from_slice(&bytes);
     buffer.push(0);
 
     address
 }
 
+//format bytes as a lowercase hex string without separators
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+//parse a hex string as written by to_hex, ignoring any whitespace
+pub fn from_hex(hex: &str) -> io::Result<Vec<u8>> {
+    let digits: Vec<u8> = hex.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Hex string has an odd number of digits: {}", hex),
+        ));
+    }
+
+    digits
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Invalid hex string: {}", hex),
+                    )
+                })
+        })
+        .collect()
+}
+
 pub fn get_file_name(filepath: &str) -> Option<&str> {
     Path::new(filepath)
         .file_name()
         .and_then(|f| f.to_str())
         .map(|filename| filename.split('.').next().unwrap_or(filename))
 }
+
+//the file name of the table a path is converted to or from, e.g. "t_town._dt" for
+//"json/t_town.json", for naming the table in errors
+pub fn table_file_name(path: &str) -> String {
+    format!("{}._dt", get_file_name(path).unwrap_or(path))
+}
+
+//check that no two entries of a table share an id, as the game and the other tables look entries
+//up by id
+pub fn ensure_unique_ids(kind: &str, ids: impl IntoIterator<Item = u16>) -> io::Result<()> {
+    let mut seen = HashSet::new();
+    match ids.into_iter().find(|&id| !seen.insert(id)) {
+        Some(id) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} id {} is used by more than one entry", kind, id),
+        )),
+        None => Ok(()),
+    }
+}
+
+//read a little endian u16 from the current position of a reader
+pub fn read_u16<R: Read>(reader: &mut R) -> io::Result<u16> {
+    let mut bytes = [0u8; 2];
+    reader.read_exact(&mut bytes)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+//read a little endian u32 from the current position of a reader
+pub fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+//read the list of u16 pointers at the beginning of a table, the first of which is also the length
+//of the list in bytes
+pub fn read_pointer_list<R: Read + Seek>(reader: &mut R) -> io::Result<Vec<u16>> {
+    reader.seek(SeekFrom::Start(0))?;
+    let header_length = read_u16(reader)?;
+    if header_length % 2 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Pointer list length {} is not even", header_length),
+        ));
+    }
+
+    let mut pointers = vec![header_length];
+    for _ in 1..header_length / 2 {
+        pointers.push(read_u16(reader)?);
+    }
+
+    Ok(pointers)
+}
+
+//the length in bytes of the records pointer i of a pointer list leads to, for tables whose records
+//run until the next pointer (or the end of the data). what names the records in errors,
+//e.g. "growth curve of character 3"
+pub fn pointed_length(
+    pointers: &[u16],
+    i: usize,
+    data_length: usize,
+    what: &str,
+) -> io::Result<usize> {
+    let start = pointers[i] as usize;
+    let end = pointers
+        .get(i + 1)
+        .map_or(data_length, |&next| next as usize);
+    end.checked_sub(start).ok_or_else(|| {
+        let next = match pointers.get(i + 1) {
+            Some(next) => format!("pointer {}, {:#06X}", i + 1, next),
+            None => format!("the end of the file at {:#06X}", data_length),
+        };
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Pointer {} to the {} is {:#06X}, past {}",
+                i, what, start, next
+            ),
+        )
+    })
+}
+
+//create a progress bar using the style shared by all of the table conversions
+pub fn progress_bar(length: u64) -> ProgressBar {
+    let style = ProgressStyle::default_bar()
+        .template("[{bar:40.cyan/blue}] {prefix} {pos}/{len}")
+        .unwrap()
+        .progress_chars("█🮆🮅🮄▀🮃🮂▔ ");
+    ProgressBar::new(length).with_style(style)
+}
+
+//write data to a file in the working directory named after the input file, with the given
+//extension
+pub fn write_output(input_path: &str, extension: &str, data: &[u8]) -> io::Result<()> {
+    let file_name = get_file_name(input_path)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Not a valid file path."))?;
+
+    let mut output = File::create(format!("{}.{}", file_name, extension))?;
+    output.write_all(data)?;
+    output.flush()
+}
+
+//compare the bytes of a table with the bytes produced by re-encoding its decoded data, warning
+//about the first difference so that lossy conversions are noticed before the json is edited
+pub fn verify_round_trip(table: &str, original: &[u8], encoded: &[u8]) -> bool {
+    if original == encoded {
+        return true;
+    }
+
+    let offset = original
+        .iter()
+        .zip(encoded)
+        .position(|(a, b)| a != b)
+        .unwrap_or(original.len().min(encoded.len()));
+    eprintln!(
+        "Warning: {} does not round-trip; first difference at offset {:#06X} (original {} bytes, re-encoded {} bytes)",
+        table,
+        offset,
+        original.len(),
+        encoded.len()
+    );
+
+    false
+}
+
+//parse a table and encode the result again, warning with verify_round_trip if that doesn't give
+//back the same bytes
+pub fn parse_verified<T>(
+    table: &str,
+    data: &[u8],
+    parse: impl FnOnce(&[u8]) -> io::Result<T>,
+    encode: impl FnOnce(&T) -> io::Result<Vec<u8>>,
+) -> io::Result<T> {
+    let parsed = parse(data)?;
+    verify_round_trip(table, data, &encode(&parsed)?);
+    Ok(parsed)
+}
+
+//decode a table to json with its parser and encoder, checked with parse_verified
+pub fn verified_json<T: Serialize>(
+    table: &str,
+    data: &[u8],
+    parse: impl FnOnce(&[u8]) -> io::Result<T>,
+    encode: impl FnOnce(&T) -> io::Result<Vec<u8>>,
+) -> io::Result<String> {
+    let parsed = parse_verified(table, data, parse, encode)?;
+    serde_json::to_string_pretty(&parsed).map_err(io::Error::other)
+}
+
+//encode the json representation of a table with its encoder, normalizing its text first if that
+//is turned on
+pub fn encode_json<T: DeserializeOwned>(
+    json: &str,
+    encode: impl FnOnce(&T) -> io::Result<Vec<u8>>,
+) -> io::Result<Vec<u8>> {
+    let invalid = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+    //going through a value loses the line numbers of errors, so only do it when needed
+    let parsed: T = if normalize::enabled() {
+        let mut value: serde_json::Value = serde_json::from_str(json).map_err(invalid)?;
+        normalize::normalize_json(&mut value);
+        serde_json::from_value(value).map_err(invalid)?
+    } else {
+        serde_json::from_str(json).map_err(invalid)?
+    };
+    encode(&parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_bytes_round_trip_as_escapes() {
+        let bytes = [b'a', 0x81, 0xFF, b'b'];
+        let decoded = decode_string(&bytes).unwrap();
+        assert_eq!(decoded, "a<X:81FF>b");
+        assert_eq!(encode_string(&decoded).unwrap(), bytes);
+    }
+
+    #[test]
+    fn literal_escapes_round_trip() {
+        let bytes = b"<X:41> <b>";
+        let decoded = decode_string(bytes).unwrap();
+        assert_eq!(decoded, "<X:3C>X:41> <b>");
+        assert_eq!(encode_string(&decoded).unwrap(), bytes);
+
+        let decoded = decode_tagged_text(bytes).unwrap();
+        assert_eq!(encode_tagged_text(&decoded).unwrap(), bytes);
+    }
+
+    #[test]
+    fn typed_escapes_are_encoded_as_bytes() {
+        assert_eq!(encode_string("<X:41>").unwrap(), b"A");
+        //tags that aren't well formed are plain text
+        assert_eq!(encode_string("<X:4>").unwrap(), b"<X:4>");
+    }
+}
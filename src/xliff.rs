@@ -0,0 +1,361 @@
+use roxmltree::{Document, Node};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+
+use crate::text::{self, TextEntry};
+use crate::util;
+
+//book and item text can be exported to XLIFF 2.0 for CAT tools. every piece of text is a unit named
+//after its key from text.rs, e.g. "t_book03/2/1/0", with the text as its source. control tags like
+//<C:n>, <S:n> and <X:hex> become <ph/> inline codes whose original tag is kept in the unit's
+//<originalData>, so that tools show them as placeholders that can be moved but not mangled. on
+//import the target of every unit that has one replaces the text with that key before the table is
+//encoded again. control characters that xml 1.0 can't hold are written as <cp hex="..."/>. empty
+//text isn't exported
+const NAMESPACE: &str = "urn:oasis:names:tc:xliff:document:2.0";
+
+pub fn export_xliff(
+    path: String,
+    source_language: String,
+    target_language: String,
+) -> io::Result<()> {
+    let file_name = text::file_name(&path)?;
+    let entries = text::entries(&file_name, &fs::read(&path)?)?;
+    let xliff = entries_to_xliff(&file_name, &entries, &source_language, &target_language);
+    util::write_output(&path, "xlf", xliff.as_bytes())
+}
+
+pub fn import_xliff(path: String, xliff_path: String, output_path: String) -> io::Result<()> {
+    let translations = parse_xliff(&fs::read_to_string(&xliff_path)?).map_err(|e| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", xliff_path, e))
+    })?;
+    let data = text::apply(&text::file_name(&path)?, &fs::read(&path)?, &translations)?;
+
+    println!(
+        "Applied {} translations from {}",
+        translations.len(),
+        xliff_path
+    );
+    fs::write(output_path, data)
+}
+
+//split text into plain text and the control tags that become inline codes
+fn split_tags(text: &str) -> Vec<(bool, &str)> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+
+    while i < text.len() {
+        let rest = &text[i..];
+        let tag_length = rest.strip_prefix('<').and_then(|rest| {
+            //colors and sizes are decimal, escaped bytes are hex
+            let is_digit: fn(&u8) -> bool = match rest.get(..2)? {
+                "C:" | "S:" => u8::is_ascii_digit,
+                "X:" => u8::is_ascii_hexdigit,
+                _ => return None,
+            };
+            let end = rest.find('>')?;
+            let value = &rest[2..end];
+            (!value.is_empty() && value.as_bytes().iter().all(is_digit)).then_some(end + 2)
+        });
+
+        match tag_length {
+            Some(length) => {
+                if start < i {
+                    pieces.push((false, &text[start..i]));
+                }
+                pieces.push((true, &text[i..i + length]));
+                i += length;
+                start = i;
+            }
+            None => i += rest.chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    if start < text.len() {
+        pieces.push((false, &text[start..]));
+    }
+
+    pieces
+}
+
+//NOTE: code for writing xliff files---------------------------------------------------------------
+fn entries_to_xliff(
+    file_name: &str,
+    entries: &[TextEntry],
+    source_language: &str,
+    target_language: &str,
+) -> String {
+    let mut xliff = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xliff.push_str(&format!(
+        "<xliff xmlns=\"{}\" version=\"2.0\" srcLang=\"{}\" trgLang=\"{}\">\n",
+        NAMESPACE,
+        escape(source_language),
+        escape(target_language)
+    ));
+    xliff.push_str(&format!("  <file id=\"{}\">\n", escape(&to_id(file_name))));
+
+    for entry in entries.iter().filter(|entry| !entry.text.is_empty()) {
+        let mut original_data = String::new();
+        let mut source = String::new();
+        let mut code = 0;
+        for (is_tag, piece) in split_tags(&entry.text) {
+            if is_tag {
+                code += 1;
+                original_data.push_str(&format!(
+                    "        <data id=\"d{}\">{}</data>\n",
+                    code,
+                    escape_content(piece)
+                ));
+                source.push_str(&format!(
+                    "<ph id=\"{}\" dataRef=\"d{}\" disp=\"{}\"/>",
+                    code,
+                    code,
+                    escape(piece)
+                ));
+            } else {
+                source.push_str(&escape_content(piece));
+            }
+        }
+
+        xliff.push_str(&format!(
+            "    <unit id=\"{}\" name=\"{}\">\n",
+            escape(&to_id(&entry.key)),
+            escape(&entry.key)
+        ));
+        if !original_data.is_empty() {
+            xliff.push_str("      <originalData>\n");
+            xliff.push_str(&original_data);
+            xliff.push_str("      </originalData>\n");
+        }
+        xliff.push_str("      <segment>\n");
+        xliff.push_str(&format!("        <source>{}</source>\n", source));
+        xliff.push_str("      </segment>\n");
+        xliff.push_str("    </unit>\n");
+    }
+
+    xliff.push_str("  </file>\n");
+    xliff.push_str("</xliff>\n");
+    xliff
+}
+
+//ids have to be xml name tokens, which can't contain the slashes of the keys
+fn to_id(key: &str) -> String {
+    key.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '.' | '-' | '_' | ':') {
+                c
+            } else {
+                '.'
+            }
+        })
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+//escape text for the content of an element. xml 1.0 has no way to write most control characters,
+//so xliff's <cp/> stands in for them, and carriage returns are written as references because
+//parsers turn literal ones into line feeds
+fn escape_content(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '\r' => escaped.push_str("&#xD;"),
+            '\t' | '\n' => escaped.push(c),
+            c if c < ' ' || matches!(c, '\u{FFFE}' | '\u{FFFF}') => {
+                escaped.push_str(&format!("<cp hex=\"{:04X}\"/>", c as u32))
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+//NOTE: code for reading xliff files---------------------------------------------------------------
+//read the translations of an xliff file, keyed by unit name
+fn parse_xliff(xliff: &str) -> Result<HashMap<String, String>, String> {
+    let document = Document::parse(xliff).map_err(|e| e.to_string())?;
+    let root = document.root_element();
+    if root.tag_name().name() != "xliff"
+        || !root
+            .attribute("version")
+            .is_some_and(|v| v.starts_with("2."))
+    {
+        return Err("not an XLIFF 2 file".to_string());
+    }
+
+    let mut translations = HashMap::new();
+    for unit in root.descendants().filter(|node| is_element(node, "unit")) {
+        let name = unit
+            .attribute("name")
+            .ok_or_else(|| format!("unit {} has no name", unit.attribute("id").unwrap_or("")))?;
+
+        let mut original_data = HashMap::new();
+        for data in unit.descendants().filter(|node| is_element(node, "data")) {
+            let Some(id) = data.attribute("id") else {
+                continue;
+            };
+            let mut text = String::new();
+            read_content(&data, &HashMap::new(), &mut text)
+                .map_err(|e| format!("unit {}, data {}: {}", name, id, e))?;
+            original_data.insert(id, text);
+        }
+
+        let mut target = String::new();
+        for segment_target in unit.descendants().filter(|node| is_element(node, "target")) {
+            read_content(&segment_target, &original_data, &mut target)
+                .map_err(|e| format!("unit {}: {}", name, e))?;
+        }
+        if target.is_empty() {
+            continue;
+        }
+
+        if translations.insert(name.to_string(), target).is_some() {
+            return Err(format!("unit {} is there more than once", name));
+        }
+    }
+
+    Ok(translations)
+}
+
+//append the text of a target to a string, turning inline codes back into the tags they stand for
+fn read_content(
+    node: &Node,
+    original_data: &HashMap<&str, String>,
+    text: &mut String,
+) -> Result<(), String> {
+    for child in node.children() {
+        if child.is_text() {
+            text.push_str(child.text().unwrap_or(""));
+        } else if is_element(&child, "ph") {
+            let data_ref = child
+                .attribute("dataRef")
+                .ok_or("an inline code has no dataRef")?;
+            let original = original_data
+                .get(data_ref)
+                .ok_or_else(|| format!("inline code refers to missing data {}", data_ref))?;
+            text.push_str(original);
+        } else if is_element(&child, "cp") {
+            let hex = child.attribute("hex").ok_or("a <cp/> has no hex")?;
+            let c = u32::from_str_radix(hex, 16)
+                .ok()
+                .and_then(char::from_u32)
+                .ok_or_else(|| format!("<cp/> has an invalid code point {}", hex))?;
+            text.push(c);
+        } else if is_element(&child, "mrk") {
+            //annotations wrap text without changing it
+            read_content(&child, original_data, text)?;
+        } else if child.is_element() {
+            return Err(format!(
+                "unsupported inline element <{}>",
+                child.tag_name().name()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn is_element(node: &Node, name: &str) -> bool {
+    node.is_element()
+        && node.tag_name().name() == name
+        && node.tag_name().namespace() == Some(NAMESPACE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(content: &str) -> String {
+        format!(
+            "<xliff xmlns=\"{}\" version=\"2.0\" srcLang=\"ja\"><file id=\"f\">\
+             <unit id=\"u\" name=\"t_item2/1/name\">{}</unit></file></xliff>",
+            NAMESPACE, content
+        )
+    }
+
+    fn target(content: &str) -> String {
+        let translations = parse_xliff(&unit(content)).unwrap();
+        translations["t_item2/1/name"].clone()
+    }
+
+    #[test]
+    fn targets_read_back_the_exported_text() {
+        let entries = [
+            TextEntry {
+                key: "t_book03/2/1/0".to_string(),
+                text: "<C:2>Estelle<C:0> & \"Joshua\" <3\r\nend".to_string(),
+            },
+            TextEntry {
+                key: "t_book03/2/1/1".to_string(),
+                text: "bytes <X:04>\u{4}\u{5}\u{6} and a\ttab".to_string(),
+            },
+        ];
+        let xliff = entries_to_xliff("t_book03._dt", &entries, "ja", "en");
+        assert!(xliff.contains("<cp hex=\"0004\"/><cp hex=\"0005\"/><cp hex=\"0006\"/>"));
+
+        //a tool that copied every source into its target
+        let translated = xliff.replace("source>", "target>");
+        let translations = parse_xliff(&translated).unwrap();
+        for entry in &entries {
+            assert_eq!(translations[&entry.key], entry.text);
+        }
+    }
+
+    #[test]
+    fn entities_and_cdata_are_text() {
+        assert_eq!(
+            target("<segment><target>&amp; &lt;b&gt; &#x41;&#66; &quot;</target></segment>"),
+            "& <b> AB \""
+        );
+        assert_eq!(
+            target("<segment><target><![CDATA[a <C:1> & b]]> c</target></segment>"),
+            "a <C:1> & b c"
+        );
+    }
+
+    #[test]
+    fn inline_codes_become_their_original_tags() {
+        let content = "<originalData><data id=\"d1\">&lt;C:2&gt;</data></originalData>\
+                       <segment><target><mrk id=\"m\" translate=\"no\"><ph id=\"1\" dataRef=\"d1\"/>\
+                       Tear</mrk> Balm<cp hex=\"001F\"/></target></segment>";
+        assert_eq!(target(content), "<C:2>Tear Balm\u{1F}");
+
+        let missing = unit("<segment><target><ph id=\"1\" dataRef=\"d9\"/></target></segment>");
+        assert_eq!(
+            parse_xliff(&missing).unwrap_err(),
+            "unit t_item2/1/name: inline code refers to missing data d9"
+        );
+    }
+
+    #[test]
+    fn only_well_formed_tags_are_split() {
+        assert_eq!(
+            split_tags("<C:12>a<X:8FfE><S:2>"),
+            [
+                (true, "<C:12>"),
+                (false, "a"),
+                (true, "<X:8FfE>"),
+                (true, "<S:2>")
+            ]
+        );
+        for text in ["<C:1F>", "<S:A>", "<X:G1>", "<C:>", "<C:1", "<"] {
+            assert_eq!(split_tags(text), [(false, text)]);
+        }
+    }
+
+    #[test]
+    fn untranslated_units_are_skipped() {
+        let xliff = unit("<segment><source>Tear Balm</source></segment>");
+        assert!(parse_xliff(&xliff).unwrap().is_empty());
+    }
+}